@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+// 防闪烁模式下，连续指令之间的静默时间，期间的指令合并为最后一次
+const SETTLE: Duration = Duration::from_millis(1500);
+// 防闪烁模式下，两次动作之间的最短保持时间
+const MIN_HOLD: Duration = Duration::from_secs(5);
+
+/// 对应属性 2.4，对开关指令去抖并合并，防止自动化频繁触发舵机
+#[derive(Default)]
+pub struct AntiFlicker {
+    enabled: bool,
+    requested: Option<(bool, Instant)>,
+    last_actuation: Option<Instant>,
+}
+
+impl AntiFlicker {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        log::info!("Anti flicker: {}", enabled);
+        self.enabled = enabled;
+    }
+
+    pub fn request(&mut self, on: bool) {
+        self.requested = Some((on, Instant::now()));
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.requested.is_some()
+    }
+
+    /// 返回此刻应当执行的开关状态
+    pub fn poll(&mut self, current: bool) -> Option<bool> {
+        let (on, at) = self.requested?;
+        if on == current {
+            self.requested = None;
+            return None;
+        }
+        if self.enabled {
+            if at.elapsed() < SETTLE {
+                return None;
+            }
+            if self
+                .last_actuation
+                .is_some_and(|last| last.elapsed() < MIN_HOLD)
+            {
+                return None;
+            }
+        }
+        self.requested = None;
+        self.last_actuation = Some(Instant::now());
+        Some(on)
+    }
+}
@@ -0,0 +1,63 @@
+mod rules;
+
+pub use rules::PresenceRules;
+
+/// 扫描到的一条广播，与蓝牙协议栈无关，便于规则匹配
+#[derive(Debug, Clone)]
+pub struct Advertisement {
+    /// 形如 `aa:bb:cc:dd:ee:ff` 的小写地址
+    pub addr: String,
+    pub addr_kind: AddrKind,
+    pub rssi: i32,
+    pub name: Option<String>,
+    /// 厂商 ID 和厂商自定义数据
+    pub manufacturer: Option<(u16, Vec<u8>)>,
+    /// Eddystone 服务数据
+    pub eddystone: Option<Vec<u8>>,
+}
+
+impl Advertisement {
+    /// 按显示顺序（高位在前）的地址字节
+    pub fn addr_bytes(&self) -> Option<[u8; 6]> {
+        parse_addr(&self.addr)
+    }
+}
+
+pub fn parse_addr(addr: &str) -> Option<[u8; 6]> {
+    let mut bytes = [0; 6];
+    let mut parts = addr.split(':');
+    for byte in bytes.iter_mut() {
+        *byte = u8::from_str_radix(parts.next()?, 16).ok()?;
+    }
+    parts.next().is_none().then_some(bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrKind {
+    Public,
+    /// 随机静态地址，设备重启前不变
+    RandomStatic,
+    /// 可解析的私有地址，定期轮换
+    ResolvablePrivate,
+    /// 不可解析的私有地址
+    NonResolvable,
+}
+
+impl AddrKind {
+    pub fn new(random: bool, addr: &[u8; 6]) -> Self {
+        if !random {
+            return AddrKind::Public;
+        }
+        // 随机地址最高两位区分类型
+        match addr[0] >> 6 {
+            0b11 => AddrKind::RandomStatic,
+            0b01 => AddrKind::ResolvablePrivate,
+            _ => AddrKind::NonResolvable,
+        }
+    }
+
+    /// 地址会轮换，不能直接用来区分设备
+    pub fn rotates(&self) -> bool {
+        matches!(self, AddrKind::ResolvablePrivate | AddrKind::NonResolvable)
+    }
+}
@@ -0,0 +1,277 @@
+use super::Advertisement;
+
+// Apple 的厂商 ID，iBeacon 广播使用
+const APPLE_COMPANY_ID: u16 = 0x004C;
+// Eddystone-UID 帧类型
+const EDDYSTONE_UID: u8 = 0x00;
+
+/// 一条在场规则，给出的条件需要全部满足
+///
+/// ```json
+/// [
+///     "Mi Band 7",
+///     {"mac": "aa:bb:cc:dd:ee:ff", "rssi": -70},
+///     {"ibeacon": "fda50693-a4e2-4fb1-afcf-c6eb07647825", "major": 10001},
+///     {"eddystone": "edd1ebeac04e5defa017", "instance": "000000000001"},
+///     {"manufacturer": 911, "data": "0102"},
+///     {"name": "iPhone*", "rssi": -60}
+/// ]
+/// ```
+///
+/// 字符串等价于 `{"name": ...}`，与旧版只填写设备名称的格式兼容。
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    /// 设备地址，不区分大小写
+    mac: Option<String>,
+    /// 设备名称，支持 `*` 通配符
+    name: Option<String>,
+    /// iBeacon 的 proximity UUID
+    ibeacon: Option<String>,
+    major: Option<u16>,
+    minor: Option<u16>,
+    /// Eddystone-UID 的 namespace，十六进制
+    eddystone: Option<String>,
+    /// Eddystone-UID 的 instance，十六进制
+    instance: Option<String>,
+    /// 厂商 ID
+    manufacturer: Option<u16>,
+    /// 厂商数据的前缀，十六进制
+    data: Option<String>,
+    /// 信号强度下限，单位 dBm
+    rssi: Option<i32>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum RuleFormat {
+    Name(String),
+    Rule(Rule),
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex: Vec<u8> = hex.bytes().filter(|c| *c != b'-' && *c != b':').collect();
+    // 先检查字符，避免按字节切分非 ASCII 字符串
+    if hex.len() % 2 != 0 || !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// 只支持 `*` 通配任意个字符
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+impl Rule {
+    fn validate(&self) -> anyhow::Result<()> {
+        // 字段和解码后应有的字节数，None 表示不限长度
+        let fields = [
+            (&self.ibeacon, Some(16)),
+            (&self.eddystone, Some(10)),
+            (&self.instance, Some(6)),
+            (&self.data, None),
+        ];
+        for (hex, len) in fields {
+            let Some(hex) = hex else {
+                continue;
+            };
+            match decode_hex(hex) {
+                Some(bytes) if len.map_or(true, |len| bytes.len() == len) => {}
+                _ => anyhow::bail!("Invalid hex string: {}", hex),
+            }
+        }
+        if self.mac.is_none()
+            && self.name.is_none()
+            && self.ibeacon.is_none()
+            && self.eddystone.is_none()
+            && self.manufacturer.is_none()
+        {
+            anyhow::bail!("Rule has no identifier: {:?}", self);
+        }
+        Ok(())
+    }
+
+    fn matches_ibeacon(&self, uuid: &str, adv: &Advertisement) -> bool {
+        // 02 15 | UUID (16) | major (2) | minor (2) | tx power (1)
+        let Some((APPLE_COMPANY_ID, payload)) = &adv.manufacturer else {
+            return false;
+        };
+        if payload.len() < 22 || payload[0..2] != [0x02, 0x15] {
+            return false;
+        }
+        let major = u16::from_be_bytes([payload[18], payload[19]]);
+        let minor = u16::from_be_bytes([payload[20], payload[21]]);
+        decode_hex(uuid).is_some_and(|uuid| payload[2..18] == uuid[..])
+            && self.major.map_or(true, |x| x == major)
+            && self.minor.map_or(true, |x| x == minor)
+    }
+
+    fn matches_eddystone(&self, namespace: &str, adv: &Advertisement) -> bool {
+        // 00 | tx power (1) | namespace (10) | instance (6)
+        let Some(frame) = &adv.eddystone else {
+            return false;
+        };
+        if frame.len() < 18 || frame[0] != EDDYSTONE_UID {
+            return false;
+        }
+        decode_hex(namespace).is_some_and(|x| frame[2..12] == x[..])
+            && self.instance.as_ref().map_or(true, |x| {
+                decode_hex(x).is_some_and(|x| frame[12..18] == x[..])
+            })
+    }
+
+    fn matches(&self, adv: &Advertisement) -> bool {
+        if self.rssi.is_some_and(|rssi| adv.rssi < rssi) {
+            return false;
+        }
+        if let Some(mac) = &self.mac {
+            if !mac.eq_ignore_ascii_case(&adv.addr) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.name {
+            if !adv
+                .name
+                .as_ref()
+                .is_some_and(|name| glob_match(pattern, name))
+            {
+                return false;
+            }
+        }
+        if let Some(uuid) = &self.ibeacon {
+            if !self.matches_ibeacon(uuid, adv) {
+                return false;
+            }
+        }
+        if let Some(namespace) = &self.eddystone {
+            if !self.matches_eddystone(namespace, adv) {
+                return false;
+            }
+        }
+        if let Some(company) = self.manufacturer {
+            let prefix = self
+                .data
+                .as_ref()
+                .and_then(|x| decode_hex(x))
+                .unwrap_or_default();
+            match &adv.manufacturer {
+                Some((id, payload)) if *id == company && payload.starts_with(&prefix) => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// 属性 7.4 中配置的在场规则，每条规则对应一个目标设备
+#[derive(Debug, Clone, Default)]
+pub struct PresenceRules {
+    rules: Vec<Rule>,
+}
+
+impl PresenceRules {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let rules: Vec<RuleFormat> = serde_json::from_str(json)?;
+        let rules: Vec<Rule> = rules
+            .into_iter()
+            .map(|rule| match rule {
+                RuleFormat::Name(name) => Rule {
+                    name: Some(name),
+                    ..Default::default()
+                },
+                RuleFormat::Rule(rule) => rule,
+            })
+            .collect();
+        for rule in rules.iter() {
+            rule.validate()?;
+        }
+        Ok(Self { rules })
+    }
+
+    /// 与广播匹配的规则序号
+    pub fn matching<'a>(&'a self, adv: &'a Advertisement) -> impl Iterator<Item = usize> + 'a {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(move |(_, rule)| rule.matches(adv))
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble::AddrKind;
+
+    fn adv() -> Advertisement {
+        Advertisement {
+            addr: "aa:bb:cc:dd:ee:ff".to_string(),
+            addr_kind: AddrKind::Public,
+            rssi: -60,
+            name: Some("iPhone 15".to_string()),
+            manufacturer: None,
+            eddystone: None,
+        }
+    }
+
+    #[test]
+    fn legacy_names_still_parse() {
+        let rules = PresenceRules::parse(r#"["iPhone*", "Mi Band 7"]"#).unwrap();
+        assert_eq!(rules.matching(&adv()).collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn rejects_invalid_hex() {
+        assert!(PresenceRules::parse(r#"[{"manufacturer": 911, "data": "é1"}]"#).is_err());
+        assert!(PresenceRules::parse(r#"[{"eddystone": "edd1ebeac04e5defa0"}]"#).is_err());
+        assert!(PresenceRules::parse(r#"[{"ibeacon": "fda50693"}]"#).is_err());
+        assert!(PresenceRules::parse(r#"[{"rssi": -70}]"#).is_err());
+    }
+
+    #[test]
+    fn matches_ibeacon() {
+        let rules = PresenceRules::parse(
+            r#"[{"ibeacon": "fda50693-a4e2-4fb1-afcf-c6eb07647825", "major": 10001}]"#,
+        )
+        .unwrap();
+        let mut payload = vec![0x02, 0x15];
+        payload.extend(decode_hex("fda50693a4e24fb1afcfc6eb07647825").unwrap());
+        payload.extend([0x27, 0x11, 0x00, 0x01, 0xc5]);
+        let mut beacon = adv();
+        beacon.manufacturer = Some((APPLE_COMPANY_ID, payload.clone()));
+        assert_eq!(rules.matching(&beacon).count(), 1);
+        payload[18] = 0;
+        beacon.manufacturer = Some((APPLE_COMPANY_ID, payload));
+        assert_eq!(rules.matching(&beacon).count(), 0);
+    }
+
+    #[test]
+    fn rssi_threshold() {
+        let rules = PresenceRules::parse(r#"[{"mac": "AA:BB:CC:DD:EE:FF", "rssi": -50}]"#).unwrap();
+        let mut near = adv();
+        assert_eq!(rules.matching(&near).count(), 0);
+        near.rssi = -40;
+        assert_eq!(rules.matching(&near).count(), 1);
+    }
+}
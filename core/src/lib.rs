@@ -0,0 +1,6 @@
+//! 与硬件无关的逻辑，可以在主机上运行单元测试：`scripts/test.sh`
+
+pub mod antiflicker;
+pub mod ble;
+pub mod sampling;
+pub mod touch;
@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplingConfig {
+    /// 采样间隔，单位 ms
+    pub interval_ms: u32,
+    /// 每次采样连续读取 ADC 的次数，取平均值
+    pub oversample: u8,
+    /// 中值滤波窗口，1 表示不使用
+    pub median: u8,
+    /// 滑动平均窗口，1 表示不使用
+    pub average: u8,
+    /// 与滤波结果相差超过该值的采样视为异常值，0 表示不剔除
+    pub outlier: u16,
+    /// 连续出现这么多次异常值后认为光照确实发生了变化
+    pub outlier_limit: u8,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: 100,
+            oversample: 4,
+            median: 3,
+            average: 5,
+            outlier: 1500,
+            outlier_limit: 3,
+        }
+    }
+}
+
+impl SamplingConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(10..=5000).contains(&self.interval_ms) {
+            anyhow::bail!("interval_ms must be between 10 and 5000");
+        }
+        if self.oversample == 0 || self.median == 0 || self.average == 0 {
+            anyhow::bail!("oversample, median and average must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// 过采样平均后的读数，响应快，供触摸检测使用
+    pub raw: u16,
+    /// 经过异常值剔除、中值和滑动平均滤波后的读数，供亮度属性使用
+    pub filtered: u16,
+}
+
+/// 光线传感器的采样处理，不依赖硬件，可以直接用合成的读数驱动
+pub struct SamplingPipeline {
+    config: SamplingConfig,
+    median: VecDeque<u16>,
+    average: VecDeque<u16>,
+    filtered: Option<u16>,
+    outliers: u8,
+}
+
+impl SamplingPipeline {
+    pub fn new(config: SamplingConfig) -> Self {
+        Self {
+            config,
+            median: VecDeque::new(),
+            average: VecDeque::new(),
+            filtered: None,
+            outliers: 0,
+        }
+    }
+
+    pub fn config(&self) -> SamplingConfig {
+        self.config
+    }
+
+    /// 更换配置并清空滤波器状态
+    pub fn set_config(&mut self, config: SamplingConfig) {
+        *self = Self::new(config);
+    }
+
+    /// 输入一次采样中过采样得到的所有读数，没有有效读数时返回 None
+    pub fn push(&mut self, readings: &[u16]) -> Option<Sample> {
+        if readings.is_empty() {
+            return None;
+        }
+        let raw = (readings.iter().map(|&x| x as u32).sum::<u32>() / readings.len() as u32) as u16;
+
+        if let Some(filtered) = self.filtered {
+            if self.config.outlier > 0 && raw.abs_diff(filtered) > self.config.outlier {
+                self.outliers += 1;
+                if self.outliers < self.config.outlier_limit {
+                    return Some(Sample { raw, filtered });
+                }
+                // 持续偏离说明光照确实变了，丢弃旧的窗口重新开始
+                self.median.clear();
+                self.average.clear();
+            }
+        }
+        self.outliers = 0;
+
+        push_window(&mut self.median, raw, self.config.median);
+        let mut sorted: Vec<u16> = self.median.iter().copied().collect();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2];
+
+        push_window(&mut self.average, median, self.config.average);
+        let filtered = (self.average.iter().map(|&x| x as u32).sum::<u32>()
+            / self.average.len() as u32) as u16;
+        self.filtered = Some(filtered);
+        Some(Sample { raw, filtered })
+    }
+}
+
+fn push_window(window: &mut VecDeque<u16>, value: u16, size: u8) {
+    window.push_back(value);
+    while window.len() > size.max(1) as usize {
+        window.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filtered(pipeline: &mut SamplingPipeline, values: &[u16]) -> Vec<u16> {
+        values
+            .iter()
+            .map(|&value| pipeline.push(&[value]).unwrap().filtered)
+            .collect()
+    }
+
+    #[test]
+    fn averages_oversampled_readings() {
+        let mut pipeline = SamplingPipeline::new(SamplingConfig::default());
+        let sample = pipeline.push(&[1000, 1010, 990, 1000]).unwrap();
+        assert_eq!(sample.raw, 1000);
+        assert_eq!(sample.filtered, 1000);
+    }
+
+    #[test]
+    fn no_readings() {
+        let mut pipeline = SamplingPipeline::new(SamplingConfig::default());
+        assert!(pipeline.push(&[]).is_none());
+    }
+
+    #[test]
+    fn median_removes_single_spike() {
+        let mut pipeline = SamplingPipeline::new(SamplingConfig {
+            average: 1,
+            ..Default::default()
+        });
+        let output = filtered(&mut pipeline, &[1000, 1000, 1000, 2000, 1000, 1000]);
+        assert!(output.iter().all(|&x| x == 1000));
+    }
+
+    #[test]
+    fn outlier_is_held_until_it_persists() {
+        let mut pipeline = SamplingPipeline::new(SamplingConfig {
+            median: 1,
+            average: 1,
+            ..Default::default()
+        });
+        let output = filtered(&mut pipeline, &[500, 500, 3000, 500, 3000, 3000, 3000]);
+        // 单次跳变被忽略，连续 3 次后采纳新的读数
+        assert_eq!(output, vec![500, 500, 500, 500, 500, 500, 3000]);
+    }
+
+    #[test]
+    fn moving_average_smooths_steps() {
+        let mut pipeline = SamplingPipeline::new(SamplingConfig {
+            median: 1,
+            average: 4,
+            outlier: 0,
+            ..Default::default()
+        });
+        let output = filtered(
+            &mut pipeline,
+            &[1000, 1000, 1000, 1000, 1400, 1400, 1400, 1400],
+        );
+        assert_eq!(output, vec![1000, 1000, 1000, 1000, 1100, 1200, 1300, 1400]);
+    }
+
+    #[test]
+    fn set_config_resets_state() {
+        let mut pipeline = SamplingPipeline::new(SamplingConfig::default());
+        filtered(&mut pipeline, &[1000, 1000, 1000]);
+        pipeline.set_config(SamplingConfig {
+            average: 1,
+            median: 1,
+            ..Default::default()
+        });
+        assert_eq!(pipeline.push(&[3000]).unwrap().filtered, 3000);
+    }
+
+    #[test]
+    fn validate_rejects_empty_windows() {
+        assert!(SamplingConfig::default().validate().is_ok());
+        assert!(SamplingConfig {
+            median: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(SamplingConfig {
+            interval_ms: 1,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TouchConfig {
+    /// 遮挡传感器时 ADC 读数相对基线的最小升幅，越小越灵敏
+    pub sensitivity: u16,
+    /// 小于该变化量的波动视为噪声
+    pub noise: u16,
+    /// 基线跟随环境光变化的时间常数，单位 ms，越小跟随越快
+    pub baseline_ms: u32,
+    /// 关灯后的冷却时间，期间光照突变不视为触摸，单位 ms
+    pub cooldown_ms: u32,
+}
+
+impl Default for TouchConfig {
+    fn default() -> Self {
+        Self {
+            sensitivity: 1000,
+            noise: 50,
+            baseline_ms: 10000,
+            cooldown_ms: 2000,
+        }
+    }
+}
+
+impl TouchConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.sensitivity == 0 || self.noise >= self.sensitivity {
+            anyhow::bail!("sensitivity must be greater than noise");
+        }
+        if !(100..=10 * 60 * 1000).contains(&self.baseline_ms) {
+            anyhow::bail!("baseline_ms must be between 100 and 600000");
+        }
+        if self.cooldown_ms > 60000 {
+            anyhow::bail!("cooldown_ms must not exceed 60000");
+        }
+        Ok(())
+    }
+}
+
+/// 通过遮挡光线传感器实现的触摸检测
+///
+/// 基线按指数加权平均跟随环境光缓慢变化，读数突然高于基线（变暗）时视为一次触摸，
+/// 回落到基线附近后才允许下一次触发。不依赖硬件，可以直接用录制的 ADC 读数驱动。
+pub struct TouchDetector {
+    config: TouchConfig,
+    baseline: Option<f32>,
+    touching: bool,
+}
+
+impl TouchDetector {
+    pub fn new(config: TouchConfig) -> Self {
+        Self {
+            config,
+            baseline: None,
+            touching: false,
+        }
+    }
+
+    pub fn config(&self) -> TouchConfig {
+        self.config
+    }
+
+    /// 修改参数后保留当前基线
+    pub fn set_config(&mut self, config: TouchConfig) {
+        self.config = config;
+    }
+
+    #[allow(dead_code)]
+    pub fn baseline(&self) -> Option<u16> {
+        self.baseline.map(|x| x as u16)
+    }
+
+    pub fn is_touching(&self) -> bool {
+        self.touching
+    }
+
+    /// 输入一个 ADC 读数，`interval` 为采样间隔，`since_close` 为距上次关灯的时间，返回是否检测到触摸
+    pub fn feed(&mut self, value: u16, interval: Duration, since_close: Option<Duration>) -> bool {
+        let value_ = value as f32;
+        let Some(baseline) = self.baseline else {
+            self.baseline = Some(value_);
+            return false;
+        };
+
+        // 关灯导致的变暗不是触摸，直接以当前读数作为新的基线
+        if since_close.is_some_and(|x| x < Duration::from_millis(self.config.cooldown_ms as u64)) {
+            self.baseline = Some(value_);
+            self.touching = false;
+            return false;
+        }
+
+        let delta = value_ - baseline;
+        if self.touching {
+            // 遮挡期间不更新基线，读数回落到一半灵敏度以内视为松开
+            if delta < self.config.sensitivity as f32 / 2.0 {
+                self.touching = false;
+            }
+            return false;
+        }
+        if delta > self.config.sensitivity as f32 {
+            self.touching = true;
+            return true;
+        }
+        if delta.abs() > self.config.noise as f32 {
+            // 按采样间隔换算每次的加权系数，修改采样间隔后跟随速度不变
+            let alpha =
+                1.0 - (-interval.as_secs_f32() * 1000.0 / self.config.baseline_ms as f32).exp();
+            self.baseline = Some(baseline + (value_ - baseline) * alpha);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 录制时光线传感器的采样间隔
+    const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// 回放一段 ADC 读数，返回检测到触摸的读数序号
+    fn replay(trace: &str) -> Vec<usize> {
+        let mut detector = TouchDetector::new(TouchConfig::default());
+        let mut closed_at = None;
+        let mut touches = vec![];
+        let samples = trace
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for (i, line) in samples.enumerate() {
+            let mut parts = line.split_whitespace();
+            let value = parts.next().unwrap().parse().unwrap();
+            if parts.next() == Some("close") {
+                closed_at = Some(i);
+            }
+            let since_close = closed_at.map(|at| SAMPLE_INTERVAL * (i - at) as u32);
+            if detector.feed(value, SAMPLE_INTERVAL, since_close) {
+                touches.push(i);
+            }
+        }
+        touches
+    }
+
+    #[test]
+    fn single_tap() {
+        assert_eq!(replay(include_str!("../traces/touch/tap.txt")), vec![31]);
+    }
+
+    #[test]
+    fn double_tap() {
+        assert_eq!(
+            replay(include_str!("../traces/touch/double_tap.txt")).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn dusk_is_not_a_touch() {
+        assert!(replay(include_str!("../traces/touch/dusk.txt")).is_empty());
+    }
+
+    #[test]
+    fn flicker_is_not_a_touch() {
+        assert!(replay(include_str!("../traces/touch/flicker.txt")).is_empty());
+    }
+
+    #[test]
+    fn lamp_off_is_not_a_touch() {
+        assert_eq!(
+            replay(include_str!("../traces/touch/lamp_off.txt")),
+            vec![71]
+        );
+    }
+
+    #[test]
+    fn baseline_follows_ambient_light() {
+        let mut detector = TouchDetector::new(TouchConfig::default());
+        for _ in 0..200 {
+            detector.feed(1500, SAMPLE_INTERVAL, None);
+        }
+        for _ in 0..200 {
+            detector.feed(1800, SAMPLE_INTERVAL, None);
+        }
+        assert!(detector.baseline().unwrap() > 1700);
+        assert!(!detector.is_touching());
+    }
+
+    #[test]
+    fn baseline_speed_does_not_depend_on_interval() {
+        let mut fast = TouchDetector::new(TouchConfig::default());
+        let mut slow = TouchDetector::new(TouchConfig::default());
+        fast.feed(1500, Duration::from_millis(100), None);
+        slow.feed(1500, Duration::from_millis(500), None);
+        // 同样经过 10 秒
+        for _ in 0..100 {
+            fast.feed(1800, Duration::from_millis(100), None);
+        }
+        for _ in 0..20 {
+            slow.feed(1800, Duration::from_millis(500), None);
+        }
+        assert!(fast.baseline().unwrap().abs_diff(slow.baseline().unwrap()) < 10);
+    }
+}
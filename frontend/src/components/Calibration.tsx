@@ -0,0 +1,141 @@
+import { useState, useEffect } from "preact/hooks"
+
+const POSITIONS = [
+    { key: "on", label: "开" },
+    { key: "neutral", label: "中间" },
+    { key: "off", label: "关" },
+]
+
+const JOG_STEPS = [-0.002, -0.0005, 0.0005, 0.002]
+
+export default function Component() {
+    const [channel, setChannel] = useState(0)
+    const [status, setStatus] = useState(null)
+    const [busy, setBusy] = useState(false)
+    const [errorMsg, setErrorMsg] = useState('')
+    const [message, setMessage] = useState('')
+    const [token, setToken] = useState(localStorage.getItem('token') || '')
+
+    function updateToken(value) {
+        setToken(value)
+        localStorage.setItem('token', value)
+    }
+
+    async function request(url, body = null) {
+        setBusy(true)
+        setErrorMsg('')
+        try {
+            const response = await fetch(url, body === null ? {} : {
+                method: 'POST',
+                headers: {
+                    'Content-Type': 'application/x-www-form-urlencoded'
+                },
+                body: `${body}&token=${encodeURIComponent(token)}`
+            })
+            const result = await response.json()
+            if (result.code) {
+                setErrorMsg(result.message)
+                return false
+            }
+            setStatus(result)
+            return true
+        } catch (error) {
+            console.error(error)
+            setErrorMsg('连接失败: ' + error.message)
+            return false
+        } finally {
+            setBusy(false)
+        }
+    }
+
+    useEffect(() => {
+        request(`/api/servo?channel=${channel}`)
+    }, [channel])
+
+    const jog = (delta) => request('/api/servo/jog', `channel=${channel}&delta=${delta}`)
+    const preview = (position) => request('/api/servo/preview', `channel=${channel}&position=${position}`)
+    async function save(position, label) {
+        setMessage('')
+        if (await request('/api/servo/save', `channel=${channel}&position=${position}`)) {
+            setMessage(`已保存为「${label}」位置`)
+        }
+    }
+
+    const buttonClass = "flex-1 justify-center rounded-md border border-gray-300 py-2 px-3 text-sm font-medium text-gray-700 shadow-sm hover:bg-gray-50 dark:border-gray-700 dark:bg-gray-800 dark:text-gray-200 dark:hover:bg-gray-700 disabled:opacity-50 disabled:cursor-not-allowed"
+    const primaryClass = "flex-1 justify-center rounded-md border border-transparent bg-indigo-600 py-2 px-3 text-sm font-medium text-white shadow-sm hover:bg-indigo-700 dark:bg-indigo-500 dark:hover:bg-indigo-600 disabled:opacity-50 disabled:cursor-not-allowed"
+
+    return (
+        <div className="min-h-[75vh] flex flex-col items-center justify-center px-4 py-12">
+            <div className="w-full max-w-md space-y-8">
+                <div className="text-center">
+                    <h1 className="text-3xl font-bold tracking-tight text-gray-900 dark:text-gray-50">
+                        舵机校准
+                    </h1>
+                    <p className="mt-2 text-sm text-gray-600 dark:text-gray-400">
+                        微调舵机位置，确认合适后保存为开、关或中间位置
+                    </p>
+                </div>
+                {errorMsg && (<div className="text-red-500 dark:text-red-400 text-center text-sm">
+                    {errorMsg}
+                </div>)}
+                {message && (<div className="text-green-600 dark:text-green-400 text-center text-sm">
+                    {message}
+                </div>)}
+                <div>
+                    <div className="mb-2 text-sm font-medium text-gray-700 dark:text-gray-300">访问令牌</div>
+                    <input
+                        type="password"
+                        value={token}
+                        onInput={(e) => updateToken(e.currentTarget.value)}
+                        placeholder="配网成功时显示的令牌"
+                        className="block w-full rounded-md border border-gray-300 py-2 px-3 text-sm shadow-sm dark:border-gray-700 dark:bg-gray-800 dark:text-gray-200"
+                    />
+                </div>
+                {status && status.channels > 1 && (<div>
+                    <div className="mb-2 text-sm font-medium text-gray-700 dark:text-gray-300">开关</div>
+                    <div className="flex gap-2">
+                        {Array.from({ length: status.channels }, (_, i) => (
+                            <button key={i} disabled={busy || i === channel} onClick={() => { setMessage(''); setChannel(i) }} className={i === channel ? primaryClass : buttonClass}>
+                                第 {i + 1} 路
+                            </button>
+                        ))}
+                    </div>
+                </div>)}
+                <div className="text-center text-gray-900 dark:text-gray-50">
+                    <div className="text-sm text-gray-600 dark:text-gray-400">当前占空比</div>
+                    <div className="text-4xl font-mono">{status ? status.duty.toFixed(4) : '-'}</div>
+                </div>
+                <div>
+                    <div className="mb-2 text-sm font-medium text-gray-700 dark:text-gray-300">微调</div>
+                    <div className="flex gap-2">
+                        {JOG_STEPS.map((delta) => (
+                            <button key={delta} disabled={busy} onClick={() => jog(delta)} className={buttonClass}>
+                                {delta > 0 ? '+' : ''}{delta}
+                            </button>
+                        ))}
+                    </div>
+                </div>
+                <div>
+                    <div className="mb-2 text-sm font-medium text-gray-700 dark:text-gray-300">预览已保存的位置</div>
+                    <div className="flex gap-2">
+                        {POSITIONS.map(({ key, label }) => (
+                            <button key={key} disabled={busy} onClick={() => preview(key)} className={buttonClass}>
+                                {label}{status && ` (${status.calibration[key].toFixed(4)})`}
+                            </button>
+                        ))}
+                    </div>
+                </div>
+                <div>
+                    <div className="mb-2 text-sm font-medium text-gray-700 dark:text-gray-300">保存当前位置为</div>
+                    <div className="flex gap-2">
+                        {POSITIONS.map(({ key, label }) => (
+                            <button key={key} disabled={busy} onClick={() => save(key, label)} className={primaryClass}>
+                                {label}
+                            </button>
+                        ))}
+                    </div>
+                </div>
+            </div>
+        </div>
+    )
+}
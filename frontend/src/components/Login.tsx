@@ -7,6 +7,8 @@ export default function Component() {
     const passwordRef = useRef(null)
     const [errorMsg, setErrorMsg] = useState('')
     const [loggedIn, setLoggedIn] = useState(false)
+    const [token, setToken] = useState('')
+    const [pin, setPin] = useState('')
 
     async function submit(e) {
         e.preventDefault()
@@ -23,6 +25,8 @@ export default function Component() {
             if (result.code) {
                 setErrorMsg(result.message)
             } else {
+                setToken(result.token)
+                setPin(result.pin)
                 setLoggedIn(true)
             }
         } catch (error) {
@@ -55,6 +59,18 @@ export default function Component() {
                         <h1 className="text-2xl font-bold tracking-tight text-gray-900 dark:text-gray-50">
                             登录成功
                         </h1>
+                        <p className="mt-2 text-sm text-gray-600 dark:text-gray-400">
+                            本地接口访问令牌（请记下，校准舵机和修改设置时需要）
+                        </p>
+                        <p className="mt-1 text-xl font-mono text-gray-900 dark:text-gray-50">
+                            {token}
+                        </p>
+                        <p className="mt-2 text-sm text-gray-600 dark:text-gray-400">
+                            蓝牙配对 PIN
+                        </p>
+                        <p className="mt-1 text-xl font-mono text-gray-900 dark:text-gray-50">
+                            {pin}
+                        </p>
                         <p className="mt-2 text-xs text-gray-600 dark:text-gray-400">
                             现在您可以断开 Smart Light Wi-Fi 连接
                         </p>
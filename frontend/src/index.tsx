@@ -1,8 +1,12 @@
 import { render } from 'preact';
 import Login from './components/Login';
+import Calibration from './components/Calibration';
 import './style.css';
 
 export function App() {
+	if (location.pathname.startsWith('/calibration')) {
+		return <Calibration />;
+	}
 	return (
 		<Login />
 	);
@@ -0,0 +1,5 @@
+#!/bin/bash
+
+# core 与硬件无关，使用主机工具链测试，覆盖 rust-toolchain.toml 和 .cargo/config.toml 中的 esp 目标
+host=$(rustc +stable -vV | sed -n 's/^host: //p')
+cargo +stable test --manifest-path core/Cargo.toml --target "$host" "$@"
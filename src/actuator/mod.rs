@@ -0,0 +1,37 @@
+mod motion;
+mod servo;
+
+pub use motion::Easing;
+pub use servo::{ServoActuator, ServoCalibration, ServoMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Position {
+    On,
+    Off,
+    Neutral,
+}
+
+impl From<bool> for Position {
+    fn from(on: bool) -> Self {
+        if on {
+            Position::On
+        } else {
+            Position::Off
+        }
+    }
+}
+
+/// 拨动墙壁开关的执行机构
+pub trait Actuator {
+    fn move_to(&mut self, position: Position) -> anyhow::Result<()>;
+
+    /// 上一次动作没有生效时再按一次
+    fn retry(&mut self, position: Position) -> anyhow::Result<()> {
+        self.move_to(position)
+    }
+
+    /// 由主循环周期性调用，返回后台动作中出现的错误
+    fn tick(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use esp_idf_hal::ledc::LedcDriver;
+use std::{sync::mpsc, thread, time::Duration};
+
+// 舵机 PWM 周期为 20ms，更快地更新占空比没有意义
+const STEP: Duration = Duration::from_millis(20);
+// 一次动作的最长时间，太慢会让按压模式的开关迟迟没有反应
+const MAX_DURATION_MS: u32 = 3000;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    /// 直接跳到目标位置
+    Step,
+    Linear,
+    EaseInOut,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct MotionProfile {
+    pub easing: Easing,
+    /// 完成一次动作的时间
+    pub duration_ms: u32,
+    /// 最大速度，单位为占空比每秒，0 表示不限制
+    pub max_speed: f32,
+}
+
+impl Default for MotionProfile {
+    fn default() -> Self {
+        Self {
+            easing: Easing::EaseInOut,
+            duration_ms: 300,
+            max_speed: 0.0,
+        }
+    }
+}
+
+impl MotionProfile {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.duration_ms > MAX_DURATION_MS {
+            anyhow::bail!("duration_ms must not exceed {}", MAX_DURATION_MS);
+        }
+        if !self.max_speed.is_finite() || self.max_speed < 0.0 {
+            anyhow::bail!("max_speed must not be negative");
+        }
+        Ok(())
+    }
+
+    fn duration(&self, distance: f32) -> Duration {
+        if self.easing == Easing::Step {
+            return Duration::ZERO;
+        }
+        let mut ms = self.duration_ms as f32;
+        if self.max_speed > 0.0 {
+            ms = ms.max(distance.abs() / self.max_speed * 1000.0);
+        }
+        Duration::from_millis(ms as u64)
+    }
+
+    fn ease(&self, t: f32) -> f32 {
+        match self.easing {
+            Easing::Step => 1.0,
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+pub enum Segment {
+    /// 按运动曲线移动到目标占空比
+    Move(f32),
+    Wait(Duration),
+    /// 停止输出 PWM
+    Release,
+}
+
+type Command = (MotionProfile, Vec<Segment>);
+
+/// 在后台线程中逐步更新舵机的占空比，新的指令会取消正在进行的动作
+pub struct MotionTask {
+    tx: mpsc::Sender<Command>,
+    errors: mpsc::Receiver<anyhow::Error>,
+}
+
+impl MotionTask {
+    pub fn spawn(driver: LedcDriver<'static>) -> anyhow::Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let (errors_tx, errors) = mpsc::channel();
+        thread::Builder::new()
+            .stack_size(4 * 1024)
+            .spawn(move || run(driver, rx, errors_tx))?;
+        Ok(Self { tx, errors })
+    }
+
+    pub fn run(&self, profile: MotionProfile, segments: Vec<Segment>) -> anyhow::Result<()> {
+        self.tx
+            .send((profile, segments))
+            .map_err(|_| anyhow::anyhow!("Servo motion task exited"))
+    }
+
+    pub fn take_error(&self) -> Option<anyhow::Error> {
+        self.errors.try_recv().ok()
+    }
+}
+
+/// 等待一段时间，期间收到新指令则返回该指令以取消当前动作
+fn wait(rx: &mpsc::Receiver<Command>, timeout: Duration) -> Option<Command> {
+    rx.recv_timeout(timeout).ok()
+}
+
+fn execute(
+    driver: &mut LedcDriver<'static>,
+    rx: &mpsc::Receiver<Command>,
+    current: &mut Option<f32>,
+    (profile, segments): Command,
+) -> anyhow::Result<Option<Command>> {
+    let max_duty = driver.get_max_duty() as f32;
+    for segment in segments {
+        match segment {
+            Segment::Move(target) => {
+                let from = current.unwrap_or(target);
+                let steps = (profile.duration(target - from).as_millis() / STEP.as_millis()).max(1);
+                for i in 1..=steps {
+                    let duty = from + (target - from) * profile.ease(i as f32 / steps as f32);
+                    driver.set_duty((max_duty * duty) as u32)?;
+                    *current = Some(duty);
+                    if let Some(next) = wait(rx, STEP) {
+                        return Ok(Some(next));
+                    }
+                }
+            }
+            Segment::Wait(duration) => {
+                if let Some(next) = wait(rx, duration) {
+                    return Ok(Some(next));
+                }
+            }
+            Segment::Release => driver.set_duty(0)?,
+        }
+    }
+    Ok(None)
+}
+
+fn run(
+    mut driver: LedcDriver<'static>,
+    rx: mpsc::Receiver<Command>,
+    errors: mpsc::Sender<anyhow::Error>,
+) {
+    let mut current = None;
+    let mut next = rx.recv().ok();
+    while let Some(command) = next.take() {
+        next = match execute(&mut driver, &rx, &mut current, command) {
+            Ok(Some(cancelled_by)) => Some(cancelled_by),
+            Ok(None) => rx.recv().ok(),
+            Err(e) => {
+                let _ = errors.send(e);
+                rx.recv().ok()
+            }
+        };
+    }
+    log::warn!("Servo motion task exited");
+}
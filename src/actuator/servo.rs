@@ -0,0 +1,295 @@
+use anyhow::bail;
+use esp_idf_hal::ledc::LedcDriver;
+use std::time::Duration;
+
+use super::motion::{MotionProfile, MotionTask, Segment};
+use super::{Actuator, Position};
+
+// 50Hz 下 0.5ms ~ 2.5ms 的脉宽，超出范围的占空比可能让舵机堵转
+const MIN_DUTY: f32 = 0.025;
+const MAX_DUTY: f32 = 0.125;
+// 开、关两个位置之间的最小和最大距离
+const MIN_TRAVEL: f32 = 0.005;
+const MAX_TRAVEL: f32 = 0.05;
+// 校准时每次微调的最大幅度
+const MAX_JOG: f32 = 0.005;
+// 回到中间位置所需的时间，之后停止输出 PWM
+const RETURN_TIME: Duration = Duration::from_millis(500);
+// 按压模式下保持按下的时间范围
+const MIN_HOLD_MS: u32 = 100;
+const MAX_HOLD_MS: u32 = 5000;
+// 只支持一路开关时设置保存在默认键下，由第一路开关继承
+const LEGACY_KEY: &str = "ch0";
+
+/// 读取某一路舵机的设置，第一路找不到时迁移旧版本保存的设置
+fn load_setting<T>(key: &str) -> anyhow::Result<Option<T>>
+where
+    T: serde::Serialize + for<'a> serde::Deserialize<'a> + std::fmt::Debug + Clone,
+{
+    if let Some(setting) = crate::nvs::load_from::<T>(key)? {
+        return Ok(Some(setting));
+    }
+    if key != LEGACY_KEY {
+        return Ok(None);
+    }
+    let legacy = crate::nvs::load::<T>()?;
+    if let Some(setting) = legacy.clone() {
+        log::info!("Migrate servo setting {:?} to {}", setting, key);
+        crate::nvs::save_to(setting, key)?;
+    }
+    Ok(legacy)
+}
+
+/// 舵机各个位置对应的占空比
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ServoCalibration {
+    pub on: f32,
+    pub off: f32,
+    pub neutral: f32,
+}
+
+impl Default for ServoCalibration {
+    fn default() -> Self {
+        Self {
+            on: 0.028,
+            off: 0.053,
+            neutral: 0.0405,
+        }
+    }
+}
+
+impl ServoCalibration {
+    pub fn get(&self, position: Position) -> f32 {
+        match position {
+            Position::On => self.on,
+            Position::Off => self.off,
+            Position::Neutral => self.neutral,
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, duty) in [
+            ("on", self.on),
+            ("off", self.off),
+            ("neutral", self.neutral),
+        ] {
+            if !(MIN_DUTY..=MAX_DUTY).contains(&duty) {
+                bail!(
+                    "{} position {} out of range [{}, {}]",
+                    name,
+                    duty,
+                    MIN_DUTY,
+                    MAX_DUTY
+                );
+            }
+        }
+        let travel = (self.on - self.off).abs();
+        if !(MIN_TRAVEL..=MAX_TRAVEL).contains(&travel) {
+            bail!(
+                "travel between on and off {} out of range [{}, {}]",
+                travel,
+                MIN_TRAVEL,
+                MAX_TRAVEL
+            );
+        }
+        if self.neutral < self.on.min(self.off) || self.neutral > self.on.max(self.off) {
+            bail!(
+                "neutral position {} is not between on and off",
+                self.neutral
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ServoMode {
+    /// 一直保持在开/关位置
+    #[default]
+    Hold,
+    /// 按下后保持 hold_ms 毫秒，回到中间位置并停止输出 PWM，方便手动拨动开关
+    Press { hold_ms: u32 },
+}
+
+impl ServoMode {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let ServoMode::Press { hold_ms } = self {
+            if !(MIN_HOLD_MS..=MAX_HOLD_MS).contains(hold_ms) {
+                anyhow::bail!(
+                    "hold_ms must be between {} and {}",
+                    MIN_HOLD_MS,
+                    MAX_HOLD_MS
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct ServoActuator {
+    // nvs 中保存校准值等配置使用的键，每路开关各不相同
+    key: String,
+    task: MotionTask,
+    calibration: ServoCalibration,
+    mode: ServoMode,
+    profile: MotionProfile,
+    // 校准时预览的占空比
+    preview: f32,
+}
+
+impl ServoActuator {
+    pub fn new(driver: LedcDriver<'static>, key: &str) -> anyhow::Result<Self> {
+        let calibration = match load_setting::<ServoCalibration>(key)? {
+            Some(calibration) => match calibration.validate() {
+                Ok(_) => calibration,
+                Err(e) => {
+                    log::warn!("Ignore invalid servo calibration {:?}: {}", calibration, e);
+                    ServoCalibration::default()
+                }
+            },
+            None => ServoCalibration::default(),
+        };
+        let mode = load_setting::<ServoMode>(key)?.unwrap_or_default();
+        let profile = load_setting::<MotionProfile>(key)?.unwrap_or_default();
+        log::info!(
+            "Servo {} calibration: {:?}, mode: {:?}, motion profile: {:?}",
+            key,
+            calibration,
+            mode,
+            profile
+        );
+        Ok(Self {
+            key: key.to_string(),
+            task: MotionTask::spawn(driver)?,
+            calibration,
+            mode,
+            profile,
+            preview: calibration.off,
+        })
+    }
+
+    /// 按压模式下舵机位置不代表开关状态
+    pub fn is_press_mode(&self) -> bool {
+        matches!(self.mode, ServoMode::Press { .. })
+    }
+
+    pub fn mode(&self) -> ServoMode {
+        self.mode
+    }
+
+    /// 校验并保存工作模式，切换到按压模式时回到中间位置
+    pub fn set_mode(&mut self, mode: ServoMode) -> anyhow::Result<()> {
+        mode.validate()?;
+        crate::nvs::save_to(mode, &self.key)?;
+        self.mode = mode;
+        log::info!("Servo {} mode: {:?}", self.key, mode);
+        if self.is_press_mode() {
+            self.move_to(Position::Neutral)?;
+        }
+        Ok(())
+    }
+
+    pub fn calibration(&self) -> ServoCalibration {
+        self.calibration
+    }
+
+    /// 校验并保存新的校准值，无需重新烧录固件
+    pub fn calibrate(&mut self, calibration: ServoCalibration) -> anyhow::Result<()> {
+        calibration.validate()?;
+        crate::nvs::save_to(calibration, &self.key)?;
+        self.calibration = calibration;
+        log::info!("Servo {} calibrated: {:?}", self.key, calibration);
+        Ok(())
+    }
+
+    /// 移动到任意位置预览，限制在舵机的安全范围内
+    pub fn preview(&mut self, duty: f32) -> anyhow::Result<f32> {
+        let duty = duty.clamp(MIN_DUTY, MAX_DUTY);
+        self.task.run(self.profile, vec![Segment::Move(duty)])?;
+        self.preview = duty;
+        Ok(duty)
+    }
+
+    pub fn previewing(&self) -> f32 {
+        self.preview
+    }
+
+    pub fn jog(&mut self, delta: f32) -> anyhow::Result<f32> {
+        self.preview(self.preview + delta.clamp(-MAX_JOG, MAX_JOG))
+    }
+
+    /// 将预览的位置保存为开、关或中间位置
+    pub fn save_preview(&mut self, position: Position) -> anyhow::Result<ServoCalibration> {
+        let mut calibration = self.calibration;
+        match position {
+            Position::On => calibration.on = self.preview,
+            Position::Off => calibration.off = self.preview,
+            Position::Neutral => calibration.neutral = self.preview,
+        }
+        if position != Position::Neutral
+            && (calibration.neutral < calibration.on.min(calibration.off)
+                || calibration.neutral > calibration.on.max(calibration.off))
+        {
+            calibration.neutral = (calibration.on + calibration.off) / 2.0;
+            log::info!("Reset neutral position to {}", calibration.neutral);
+        }
+        self.calibrate(calibration)?;
+        Ok(calibration)
+    }
+
+    pub fn profile(&self) -> MotionProfile {
+        self.profile
+    }
+
+    /// 校验并保存运动曲线，下一次动作时生效
+    pub fn set_profile(&mut self, profile: MotionProfile) -> anyhow::Result<()> {
+        profile.validate()?;
+        crate::nvs::save_to(profile, &self.key)?;
+        self.profile = profile;
+        log::info!("Servo {} motion profile: {:?}", self.key, profile);
+        Ok(())
+    }
+}
+
+impl Actuator for ServoActuator {
+    fn move_to(&mut self, position: Position) -> anyhow::Result<()> {
+        let target = self.calibration.get(position);
+        let neutral = self.calibration.neutral;
+        let segments = match (self.mode, position) {
+            (ServoMode::Hold, _) => vec![Segment::Move(target)],
+            (ServoMode::Press { .. }, Position::Neutral) => vec![
+                Segment::Move(neutral),
+                Segment::Wait(RETURN_TIME),
+                Segment::Release,
+            ],
+            (ServoMode::Press { hold_ms }, _) => vec![
+                Segment::Move(target),
+                Segment::Wait(Duration::from_millis(hold_ms as u64)),
+                Segment::Move(neutral),
+                Segment::Wait(RETURN_TIME),
+                Segment::Release,
+            ],
+        };
+        self.task.run(self.profile, segments)
+    }
+
+    fn retry(&mut self, position: Position) -> anyhow::Result<()> {
+        if self.mode != ServoMode::Hold {
+            return self.move_to(position);
+        }
+        // 保持模式下舵机已经停在目标位置，先退回中间位置再重新拨动
+        let segments = vec![
+            Segment::Move(self.calibration.neutral),
+            Segment::Wait(RETURN_TIME),
+            Segment::Move(self.calibration.get(position)),
+        ];
+        self.task.run(self.profile, segments)
+    }
+
+    fn tick(&mut self) -> anyhow::Result<()> {
+        match self.task.take_error() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,10 +1,22 @@
 use embedded_svc::http::client::Client;
-use esp_idf_svc::http::{client::{Configuration, EspHttpConnection}, Method};
+use esp_idf_svc::http::{
+    client::{Configuration, EspHttpConnection},
+    Method,
+};
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::net::{self, NetState};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(20);
 
 #[derive(Debug, serde::Deserialize)]
 pub struct Ap {
     pub sta_count: String,
-    pub ap_name: String
+    pub ap_name: String,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -38,10 +50,53 @@ pub fn status() -> anyhow::Result<Status> {
                 .map_err(|e| anyhow::anyhow!("failed to parse response body: {}", e))?;
             let status: Status = serde_json::from_str(&body)?;
             Ok(status)
-        },
+        }
         _ => {
             log::error!("[!] Unexpected status code: {}", status);
             Err(anyhow::anyhow!("Unexpected status code: {}", status))
         }
     }
-}
\ No newline at end of file
+}
+
+/// 轮询 AP 上连接的设备数量（属性 6.1），`enabled` 为 false 时数量清零，线程挂起直到重新打开
+pub fn spawn_poller(
+    enabled: Arc<(Mutex<bool>, Condvar)>,
+    sta_count: Arc<Mutex<Option<u32>>>,
+) -> anyhow::Result<()> {
+    thread::Builder::new()
+        .stack_size(8 * 1024)
+        .spawn(move || loop {
+            {
+                let (lock, cvar) = &*enabled;
+                let mut guard = lock.lock().unwrap();
+                if !*guard {
+                    log::info!("AP status poll stopped");
+                    *sta_count.lock().unwrap() = Some(0);
+                    while !*guard {
+                        guard = cvar.wait(guard).unwrap();
+                    }
+                    log::info!("AP status poll started");
+                }
+            }
+
+            if net::state() == NetState::Online {
+                match status() {
+                    Ok(data) => {
+                        log::info!("AP status: {:?}", data);
+                        match data.ap.sta_count.parse::<u32>() {
+                            Ok(cnt) => *sta_count.lock().unwrap() = Some(cnt),
+                            Err(e) => {
+                                log::error!("Invalid sta_count {:?}: {}", data.ap.sta_count, e)
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Failed to get AP status: {:?}", e);
+                    }
+                }
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        })?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use crate::ble::PresenceEvent;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct AutoSwitchConfig {
+    /// 到达时照度低于该值才开灯，单位 lux
+    pub dark_below: f32,
+    /// 所有人离开后延迟关灯的时间，单位 s
+    pub off_delay_s: u32,
+    /// 手动开关后暂停自动控制的时间，单位 s
+    pub override_s: u32,
+}
+
+impl Default for AutoSwitchConfig {
+    fn default() -> Self {
+        Self {
+            dark_below: 50.0,
+            off_delay_s: 5 * 60,
+            override_s: 15 * 60,
+        }
+    }
+}
+
+/// 在场自动开关（属性 7.5）：有人到达且天黑时开灯，所有人离开一段时间后关灯
+///
+/// 不是由自动化发出的开关变化都视为手动操作，之后的一段时间内不再自动开关，
+/// 避免刚通过米家或触摸执行的操作被立即撤销。
+pub struct AutoSwitch {
+    enabled: bool,
+    config: AutoSwitchConfig,
+    off_at: Option<Instant>,
+    override_until: Option<Instant>,
+    // 自动化发出、尚未生效的开关指令
+    commanded: Option<bool>,
+    last_state: Option<bool>,
+}
+
+impl AutoSwitch {
+    pub fn new() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<AutoSwitchConfig>()?.unwrap_or_default();
+        log::info!("Auto switch config: {:?}", config);
+        Ok(Self {
+            enabled: false,
+            config,
+            off_at: None,
+            override_until: None,
+            commanded: None,
+            last_state: None,
+        })
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        log::info!("Presence auto switch: {}", enabled);
+        self.enabled = enabled;
+        self.off_at = None;
+    }
+
+    pub fn config(&self) -> AutoSwitchConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: AutoSwitchConfig) -> anyhow::Result<()> {
+        crate::nvs::save(config)?;
+        log::info!("Auto switch config: {:?}", config);
+        self.config = config;
+        Ok(())
+    }
+
+    /// 其他自动化（例如光照自动化）发出的指令不算作手动操作
+    pub fn note_command(&mut self, on: bool) {
+        self.commanded = Some(on);
+    }
+
+    /// 指令被拒绝（例如无线模式或故障锁定）时调用，之后的状态变化不应被当作自动化的结果
+    pub fn cancel_command(&mut self) {
+        self.commanded = None;
+    }
+
+    fn overridden(&self) -> bool {
+        self.override_until.is_some_and(|at| Instant::now() < at)
+    }
+
+    /// 每个主循环调用，`state` 为开关当前状态，`lux` 为当前照度
+    pub fn update(
+        &mut self,
+        state: bool,
+        lux: Option<f32>,
+        events: &[PresenceEvent],
+    ) -> Option<bool> {
+        if self.last_state.is_some_and(|last| last != state) {
+            if self.commanded == Some(state) {
+                self.commanded = None;
+            } else {
+                log::info!(
+                    "Manual switch detected, pause auto switch for {} s",
+                    self.config.override_s
+                );
+                self.override_until =
+                    Some(Instant::now() + Duration::from_secs(self.config.override_s as u64));
+                self.off_at = None;
+            }
+        }
+        self.last_state = Some(state);
+
+        if !self.enabled {
+            return None;
+        }
+
+        let mut command = None;
+        for event in events {
+            match event {
+                PresenceEvent::Arrived => {
+                    self.off_at = None;
+                    let dark = lux.is_some_and(|lux| lux < self.config.dark_below);
+                    if dark && !state && !self.overridden() {
+                        log::info!("Arrived in the dark, switch on");
+                        command = Some(true);
+                    }
+                }
+                PresenceEvent::Departed => {
+                    self.off_at =
+                        Some(Instant::now() + Duration::from_secs(self.config.off_delay_s as u64));
+                }
+            }
+        }
+
+        if self.off_at.is_some_and(|at| Instant::now() >= at) {
+            self.off_at = None;
+            if state && !self.overridden() {
+                log::info!("Everyone left, switch off");
+                command = Some(false);
+            }
+        }
+
+        if let Some(on) = command {
+            self.commanded = Some(on);
+        }
+        command
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver},
+    Arc,
+};
+
+use esp32_nimble::{
+    enums::{AuthReq, SecurityIOCap},
+    utilities::{mutex::Mutex, BleUuid},
+    uuid128, BLEAdvertisementData, BLECharacteristic, BLEDevice, NimbleProperties,
+};
+use esp_idf_svc::sys::esp_random;
+
+pub const DEVICE_NAME: &str = "smart-light";
+
+// 蓝牙配网期间广播由配网服务占用，控制服务暂停广播
+static PROVISIONING: AtomicBool = AtomicBool::new(false);
+
+/// 蓝牙配网开始和结束时调用
+pub fn set_provisioning(active: bool) {
+    PROVISIONING.store(active, Ordering::SeqCst);
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct GattConfig {
+    pub enabled: bool,
+    /// 配对时输入的 6 位 PIN，首次启动时随机生成
+    pub pin: u32,
+}
+
+impl GattConfig {
+    pub fn load() -> anyhow::Result<Self> {
+        if let Some(config) = crate::nvs::load::<GattConfig>()? {
+            return Ok(config);
+        }
+        let config = GattConfig {
+            enabled: true,
+            pin: unsafe { esp_random() } % 1_000_000,
+        };
+        crate::nvs::save(config)?;
+        log::info!("Generated a new BLE pairing PIN: {:06}", config.pin);
+        Ok(config)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if self.pin > 999999 {
+            anyhow::bail!("PIN must have 6 digits");
+        }
+        crate::nvs::save(*self)?;
+        log::info!("BLE control enabled: {}, restart to apply", self.enabled);
+        Ok(())
+    }
+
+    /// 要求手机输入 PIN 配对，蓝牙控制和蓝牙配网共用
+    pub fn apply_security(&self) {
+        BLEDevice::take()
+            .security()
+            .set_auth(AuthReq::all())
+            .set_passkey(self.pin)
+            .set_io_cap(SecurityIOCap::DisplayOnly)
+            .resolve_rpa();
+    }
+}
+
+/// 手机通过 GATT 写入的指令，由主循环执行
+#[derive(Debug, Clone, Copy)]
+pub enum GattCommand {
+    /// 第一路开关（属性 2.1）
+    Switch(bool),
+    /// 第一路开关的模式（属性 2.2）
+    Mode(u32),
+}
+
+/// 蓝牙本地控制服务，校园网或米家云不可用时也能用手机开关灯
+///
+/// 所有特征值都要求加密并通过 PIN 配对后才能读写：
+/// - 开关：1 字节，0 关 1 开，读写、通知
+/// - 照度：f32 小端，单位 lux，读、通知
+/// - 模式：1 字节，与属性 2.2 相同，读写、通知
+pub struct GattServer {
+    switch: Arc<Mutex<BLECharacteristic>>,
+    illumination: Arc<Mutex<BLECharacteristic>>,
+    mode: Arc<Mutex<BLECharacteristic>>,
+    last: (Option<bool>, Option<f32>, Option<u32>),
+    service_uuid: BleUuid,
+    advertising: bool,
+}
+
+impl GattServer {
+    pub fn start(config: &GattConfig) -> anyhow::Result<(Self, Receiver<GattCommand>)> {
+        let ble_device = BLEDevice::take();
+        config.apply_security();
+
+        let server = ble_device.get_server();
+        server.on_connect(|_, desc| log::info!("BLE client connected: {:?}", desc.address()));
+        server.on_disconnect(|desc, reason| {
+            log::info!(
+                "BLE client disconnected: {:?}, {:?}",
+                desc.address(),
+                reason
+            )
+        });
+        server.on_authentication_complete(|desc, result| {
+            log::info!("BLE authentication {:?}: {:?}", desc.address(), result)
+        });
+
+        let protected = NimbleProperties::READ_ENC | NimbleProperties::READ_AUTHEN;
+        let service_uuid = uuid128!("6e4a0001-6d1f-4c4a-9b1e-7c3d2a5b8f10");
+        let service = server.create_service(service_uuid);
+        let switch = service.lock().create_characteristic(
+            uuid128!("6e4a0002-6d1f-4c4a-9b1e-7c3d2a5b8f10"),
+            protected
+                | NimbleProperties::READ
+                | NimbleProperties::WRITE
+                | NimbleProperties::WRITE_ENC
+                | NimbleProperties::WRITE_AUTHEN
+                | NimbleProperties::NOTIFY,
+        );
+        let illumination = service.lock().create_characteristic(
+            uuid128!("6e4a0003-6d1f-4c4a-9b1e-7c3d2a5b8f10"),
+            protected | NimbleProperties::READ | NimbleProperties::NOTIFY,
+        );
+        let mode = service.lock().create_characteristic(
+            uuid128!("6e4a0004-6d1f-4c4a-9b1e-7c3d2a5b8f10"),
+            protected
+                | NimbleProperties::READ
+                | NimbleProperties::WRITE
+                | NimbleProperties::WRITE_ENC
+                | NimbleProperties::WRITE_AUTHEN
+                | NimbleProperties::NOTIFY,
+        );
+
+        let (tx, rx) = mpsc::channel();
+        let tx_ = tx.clone();
+        switch.lock().on_write(move |args| match args.recv_data() {
+            [value] if *value <= 1 => {
+                let _ = tx_.send(GattCommand::Switch(*value == 1));
+            }
+            _ => args.reject(),
+        });
+        mode.lock().on_write(move |args| match args.recv_data() {
+            [value] if *value <= 1 => {
+                let _ = tx.send(GattCommand::Mode(*value as u32));
+            }
+            _ => args.reject(),
+        });
+
+        let mut server = Self {
+            switch,
+            illumination,
+            mode,
+            last: (None, None, None),
+            service_uuid,
+            advertising: false,
+        };
+        server.update_advertising()?;
+        log::info!("BLE control service started");
+
+        Ok((server, rx))
+    }
+
+    /// 配网期间不广播，避免覆盖配网服务的广播数据，配网结束后恢复
+    fn update_advertising(&mut self) -> anyhow::Result<()> {
+        let provisioning = PROVISIONING.load(Ordering::SeqCst);
+        if provisioning == !self.advertising {
+            return Ok(());
+        }
+        if provisioning {
+            log::info!("Pause BLE control advertising during provisioning");
+            self.advertising = false;
+            return Ok(());
+        }
+        let mut advertising = BLEDevice::take().get_advertising().lock();
+        let _ = advertising.stop();
+        advertising.set_data(
+            BLEAdvertisementData::new()
+                .name(DEVICE_NAME)
+                .add_service_uuid(self.service_uuid),
+        )?;
+        advertising.start()?;
+        self.advertising = true;
+        Ok(())
+    }
+
+    /// 由主循环调用，数值变化时通知已订阅的手机
+    pub fn update(&mut self, switch_on: bool, lux: Option<f32>, mode: Option<u32>) {
+        if let Err(e) = self.update_advertising() {
+            log::error!("Failed to update BLE advertising: {:?}", e);
+        }
+        if self.last.0 != Some(switch_on) {
+            self.switch.lock().set_value(&[switch_on as u8]).notify();
+            self.last.0 = Some(switch_on);
+        }
+        if let Some(lux) = lux {
+            if self.last.1 != Some(lux) {
+                self.illumination
+                    .lock()
+                    .set_value(&lux.to_le_bytes())
+                    .notify();
+                self.last.1 = Some(lux);
+            }
+        }
+        if let Some(mode) = mode {
+            if self.last.2 != Some(mode) {
+                self.mode.lock().set_value(&[mode as u8]).notify();
+                self.last.2 = Some(mode);
+            }
+        }
+    }
+}
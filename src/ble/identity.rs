@@ -0,0 +1,134 @@
+use std::{collections::VecDeque, hash::Hasher};
+
+use esp_idf_svc::sys;
+use twox_hash::XxHash64;
+
+use super::{AddrKind, Advertisement};
+
+// 取最近几次扫描设备数量的中位数作为在场人数估计
+const HISTORY: usize = 5;
+
+/// 配置的身份解析密钥（IRK），十六进制，高位在前
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct IrkConfig(pub Vec<String>);
+
+fn decode_irk(hex: &str) -> anyhow::Result<[u8; 16]> {
+    let hex = hex.trim();
+    // 先检查字符，避免按字节切分非 ASCII 字符串
+    if hex.len() != 32 || !hex.bytes().all(|x| x.is_ascii_hexdigit()) {
+        anyhow::bail!("IRK must have 32 hex digits: {}", hex);
+    }
+    let mut irk = [0; 16];
+    for (byte, pair) in irk.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair)?, 16)?;
+    }
+    Ok(irk)
+}
+
+/// 蓝牙核心规范中的 ah 函数：AES-128(IRK, 0..0 || prand) 的低 24 位
+fn ah(irk: &[u8; 16], prand: &[u8]) -> Option<[u8; 3]> {
+    let mut input = [0u8; 16];
+    input[13..].copy_from_slice(prand);
+    let mut output = [0u8; 16];
+    unsafe {
+        let mut ctx = std::mem::zeroed::<sys::mbedtls_aes_context>();
+        sys::mbedtls_aes_init(&mut ctx);
+        let ok = sys::mbedtls_aes_setkey_enc(&mut ctx, irk.as_ptr(), 128) == 0
+            && sys::mbedtls_aes_crypt_ecb(
+                &mut ctx,
+                sys::MBEDTLS_AES_ENCRYPT as i32,
+                input.as_ptr(),
+                output.as_mut_ptr(),
+            ) == 0;
+        sys::mbedtls_aes_free(&mut ctx);
+        if !ok {
+            return None;
+        }
+    }
+    Some([output[13], output[14], output[15]])
+}
+
+/// 为扫描到的广播确定一个稳定的设备标识，用于统计属性 7.1
+///
+/// - 公共地址和随机静态地址直接使用地址
+/// - 可解析的私有地址优先用配置的 IRK 解析
+/// - 其余轮换的地址按广播内容（名称、厂商 ID 和完整的厂商数据）生成指纹
+pub struct DeviceIdentifier {
+    irks: Vec<[u8; 16]>,
+    history: VecDeque<usize>,
+}
+
+impl DeviceIdentifier {
+    pub fn new() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<IrkConfig>()?.unwrap_or_default();
+        let irks = config
+            .0
+            .iter()
+            .filter_map(|x| decode_irk(x).map_err(|e| log::warn!("{:?}", e)).ok())
+            .collect::<Vec<_>>();
+        log::info!("Loaded {} IRKs", irks.len());
+        Ok(Self {
+            irks,
+            history: VecDeque::new(),
+        })
+    }
+
+    pub fn set_irks(&mut self, config: IrkConfig) -> anyhow::Result<()> {
+        let irks = config
+            .0
+            .iter()
+            .map(|x| decode_irk(x))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        crate::nvs::save(config)?;
+        log::info!("Loaded {} IRKs", irks.len());
+        self.irks = irks;
+        Ok(())
+    }
+
+    pub fn irk_count(&self) -> usize {
+        self.irks.len()
+    }
+
+    fn resolve(&self, addr: &[u8; 6]) -> Option<usize> {
+        let (prand, hash) = addr.split_at(3);
+        self.irks
+            .iter()
+            .position(|irk| ah(irk, prand).is_some_and(|x| x[..] == *hash))
+    }
+
+    /// 轮换地址无法解析时按名称和完整的厂商数据区分，内容不同的广播不会被合并
+    pub fn identify(&self, adv: &Advertisement) -> String {
+        let kind = adv.addr_kind;
+        if !kind.rotates() {
+            return adv.addr.clone();
+        }
+        if kind == AddrKind::ResolvablePrivate {
+            if let Some(index) = adv.addr_bytes().and_then(|addr| self.resolve(&addr)) {
+                return format!("irk:{}", index);
+            }
+        }
+        if adv.name.is_none() && adv.manufacturer.is_none() {
+            return adv.addr.clone();
+        }
+        let mut hasher = XxHash64::default();
+        if let Some(name) = &adv.name {
+            hasher.write(name.as_bytes());
+        }
+        if let Some((id, payload)) = &adv.manufacturer {
+            hasher.write_u16(*id);
+            hasher.write(payload);
+        }
+        format!("fp:{:016x}", hasher.finish())
+    }
+
+    /// 记录一次扫描中不同设备的数量，返回平滑后的估计值
+    pub fn record_scan(&mut self, count: usize) -> usize {
+        self.history.push_back(count);
+        while self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+        let mut sorted: Vec<usize> = self.history.iter().copied().collect();
+        sorted.sort_unstable();
+        sorted[sorted.len() / 2]
+    }
+}
@@ -0,0 +1,48 @@
+mod gatt;
+mod identity;
+mod scanner;
+mod table;
+mod tracker;
+
+pub use gatt::{set_provisioning, GattCommand, GattConfig, GattServer, DEVICE_NAME};
+pub use identity::{DeviceIdentifier, IrkConfig};
+pub use scanner::{ScanConfig, ScanEvent, ScanStatus, Scanner};
+pub use smart_light_core::ble::{AddrKind, Advertisement, PresenceRules};
+pub use table::DeviceTable;
+pub use tracker::{PresenceConfig, PresenceEvent, PresenceTracker};
+
+use esp32_nimble::{
+    enums::BLEAddressType, utilities::BleUuid, BLEAdvertisedData, BLEAdvertisedDevice,
+};
+use smart_light_core::ble::parse_addr;
+
+// Eddystone 使用的 16 位服务 UUID
+const EDDYSTONE_UUID: u16 = 0xFEAA;
+
+/// 把蓝牙协议栈扫描到的广播转换为与协议栈无关的 [`Advertisement`]
+pub fn advertisement(
+    device: &BLEAdvertisedDevice,
+    data: &BLEAdvertisedData<&[u8]>,
+) -> Advertisement {
+    let addr = device.addr().to_string().to_lowercase();
+    let random = matches!(
+        device.addr().addr_type(),
+        BLEAddressType::Random | BLEAddressType::RandomID
+    );
+    let addr_kind = parse_addr(&addr)
+        .map(|bytes| AddrKind::new(random, &bytes))
+        .unwrap_or(AddrKind::Public);
+    Advertisement {
+        addr,
+        addr_kind,
+        rssi: device.rssi() as i32,
+        name: data.name().map(|name| name.to_string()),
+        manufacturer: data
+            .manufacture_data()
+            .map(|x| (x.company_identifier, x.payload.to_vec())),
+        eddystone: data
+            .service_data()
+            .filter(|x| x.uuid() == BleUuid::from_uuid16(EDDYSTONE_UUID))
+            .map(|x| x.data().to_vec()),
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use esp32_nimble::{BLEDevice, BLEScan};
+use esp_idf_hal::task::block_on;
+
+use super::{advertisement, Advertisement};
+use crate::net::{self, NetState};
+
+// 等待期间检查停止和暂停的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// 扫描连续失败时的最长退避时间
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanConfig {
+    pub enabled: bool,
+    /// 每轮扫描的时长，单位 ms
+    pub scan_ms: u32,
+    /// 两轮扫描之间的空闲时间，单位 ms
+    pub idle_ms: u32,
+    /// 扫描期间射频的扫描间隔和窗口，单位 ms，窗口占间隔的比例即扫描时的占空比
+    pub interval_ms: u16,
+    pub window_ms: u16,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            scan_ms: 10000,
+            idle_ms: 10000,
+            interval_ms: 100,
+            window_ms: 50,
+        }
+    }
+}
+
+impl ScanConfig {
+    pub fn load() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<ScanConfig>()?.unwrap_or_default();
+        log::info!("BLE scan config: {:?}", config);
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(1000..=60000).contains(&self.scan_ms) {
+            anyhow::bail!("scan_ms must be between 1000 and 60000");
+        }
+        if self.idle_ms > 10 * 60 * 1000 {
+            anyhow::bail!("idle_ms must not exceed 600000");
+        }
+        // 蓝牙规范限制扫描间隔为 2.5 ms 到 10.24 s
+        if !(3..=10240).contains(&self.interval_ms) {
+            anyhow::bail!("interval_ms must be between 3 and 10240");
+        }
+        if self.window_ms < 3 || self.window_ms > self.interval_ms {
+            anyhow::bail!("window_ms must be between 3 and interval_ms");
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.validate()?;
+        crate::nvs::save(*self)?;
+        log::info!("BLE scan config: {:?}", self);
+        Ok(())
+    }
+
+    /// 射频实际用于扫描的时间比例
+    pub fn duty_cycle(&self) -> f32 {
+        let busy = self.scan_ms as f32 / (self.scan_ms + self.idle_ms) as f32;
+        busy * self.window_ms as f32 / self.interval_ms as f32
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanState {
+    #[default]
+    Idle,
+    Scanning,
+    /// 配网期间暂停，避免干扰蓝牙配网和热点
+    Paused,
+    Disabled,
+    /// 扫描出错后等待重试
+    Backoff,
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy, Default)]
+pub struct ScanStatus {
+    pub state: ScanState,
+    /// 连续失败的次数
+    pub errors: u32,
+}
+
+pub enum ScanEvent {
+    Found(Advertisement),
+    /// 一轮扫描正常结束
+    Finished,
+    /// 一轮扫描被中途停止，本轮结果不完整
+    Aborted,
+}
+
+type EventHandler = Box<dyn FnMut(ScanEvent) + Send>;
+
+/// 在独立线程中周期性扫描蓝牙广播，扫描失败不会影响网络线程
+///
+/// `stop` 会等待扫描线程退出，之后可以再次 `start`。Drop 时同样会停止扫描。
+pub struct Scanner {
+    config: Arc<Mutex<ScanConfig>>,
+    on_event: Arc<Mutex<EventHandler>>,
+    status: Arc<Mutex<ScanStatus>>,
+    worker: Mutex<Option<(Arc<AtomicBool>, JoinHandle<()>)>>,
+}
+
+fn paused() -> bool {
+    net::state() == NetState::Provisioning
+}
+
+impl Scanner {
+    pub fn spawn<F>(config: Arc<Mutex<ScanConfig>>, on_event: F) -> anyhow::Result<Self>
+    where
+        F: FnMut(ScanEvent) + Send + 'static,
+    {
+        let scanner = Self {
+            config,
+            on_event: Arc::new(Mutex::new(Box::new(on_event))),
+            status: Arc::new(Mutex::new(ScanStatus::default())),
+            worker: Mutex::new(None),
+        };
+        scanner.start()?;
+        Ok(scanner)
+    }
+
+    /// 启动扫描线程，已经在运行时什么也不做
+    pub fn start(&self) -> anyhow::Result<()> {
+        let mut worker = self.worker.lock().unwrap();
+        if worker.is_some() {
+            return Ok(());
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_ = Arc::clone(&stop);
+        let config_ = Arc::clone(&self.config);
+        let on_event = Arc::clone(&self.on_event);
+        let status_ = Arc::clone(&self.status);
+
+        let handle = thread::Builder::new().stack_size(8 * 1024).spawn(move || {
+            let set_state = |state| status_.lock().unwrap().state = state;
+            let mut on_event = on_event.lock().unwrap();
+            while !stop_.load(Ordering::Relaxed) {
+                let config = *config_.lock().unwrap();
+                if !config.enabled {
+                    set_state(ScanState::Disabled);
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                if paused() {
+                    set_state(ScanState::Paused);
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+
+                set_state(ScanState::Scanning);
+                let wait = match block_on(scan(&config, &stop_, &mut *on_event)) {
+                    Ok(()) => {
+                        status_.lock().unwrap().errors = 0;
+                        set_state(ScanState::Idle);
+                        Duration::from_millis(config.idle_ms as u64)
+                    }
+                    Err(e) => {
+                        let errors = {
+                            let mut status = status_.lock().unwrap();
+                            status.errors += 1;
+                            status.errors
+                        };
+                        let backoff = (Duration::from_millis(config.idle_ms.max(1000) as u64)
+                            * 2u32.pow(errors.min(8)))
+                        .min(MAX_BACKOFF);
+                        log::error!(
+                            "BLE scan failed ({} errors), retry in {:?}: {:?}",
+                            errors,
+                            backoff,
+                            e
+                        );
+                        on_event(ScanEvent::Aborted);
+                        set_state(ScanState::Backoff);
+                        backoff
+                    }
+                };
+
+                // 等待期间修改配置（包括关闭扫描）立即生效，不必等到空闲或退避结束
+                let mut waited = Duration::ZERO;
+                while waited < wait
+                    && !stop_.load(Ordering::Relaxed)
+                    && *config_.lock().unwrap() == config
+                {
+                    let step = POLL_INTERVAL.min(wait - waited);
+                    thread::sleep(step);
+                    waited += step;
+                }
+            }
+            set_state(ScanState::Paused);
+            log::info!("BLE scanner stopped");
+        })?;
+        *worker = Some((stop, handle));
+        Ok(())
+    }
+
+    /// 停止扫描并等待扫描线程退出，正在进行的一轮扫描会在收到下一条广播或扫描时长结束时停止
+    pub fn stop(&self) {
+        let Some((stop, handle)) = self.worker.lock().unwrap().take() else {
+            return;
+        };
+        stop.store(true, Ordering::Relaxed);
+        if handle.join().is_err() {
+            log::error!("BLE scanner thread panicked");
+        }
+    }
+
+    pub fn status(&self) -> Arc<Mutex<ScanStatus>> {
+        Arc::clone(&self.status)
+    }
+}
+
+impl Drop for Scanner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn scan<F>(config: &ScanConfig, stop: &AtomicBool, on_event: &mut F) -> anyhow::Result<()>
+where
+    F: FnMut(ScanEvent),
+{
+    let mut ble_scan = BLEScan::new();
+    ble_scan
+        .interval(config.interval_ms)
+        .window(config.window_ms);
+    let aborted = ble_scan
+        .start(BLEDevice::take(), config.scan_ms as i32, |device, data| {
+            // 中途开始配网或被停止时立即结束本轮扫描
+            if stop.load(Ordering::Relaxed) || paused() {
+                return Some(());
+            }
+            on_event(ScanEvent::Found(advertisement(device, &data)));
+            None
+        })
+        .await?;
+    // 不完整的一轮扫描会让在场检测误判设备离开，交给调用方丢弃
+    on_event(match aborted {
+        Some(()) => ScanEvent::Aborted,
+        None => ScanEvent::Finished,
+    });
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use super::Advertisement;
+
+// 表中最多保存的设备数量，超出后淘汰最久未见的设备
+const MAX_DEVICES: usize = 64;
+// 超过该时间未见的设备会被移除
+const EXPIRE: Duration = Duration::from_secs(5 * 60);
+
+struct Entry {
+    adv: Advertisement,
+    last_seen: Instant,
+}
+
+/// 最近扫描到的蓝牙设备，便于查找填写在场规则所需的标识
+#[derive(Default)]
+pub struct DeviceTable {
+    devices: HashMap<String, Entry>,
+}
+
+impl DeviceTable {
+    pub fn insert(&mut self, adv: &Advertisement) {
+        let now = Instant::now();
+        if let Some(entry) = self.devices.get_mut(&adv.addr) {
+            // 有的设备名称和厂商数据分别在广播和扫描响应中，保留之前见过的
+            let name = adv.name.clone().or(entry.adv.name.take());
+            let manufacturer = adv.manufacturer.clone().or(entry.adv.manufacturer.take());
+            entry.adv = Advertisement {
+                name,
+                manufacturer,
+                ..adv.clone()
+            };
+            entry.last_seen = now;
+            return;
+        }
+        self.devices
+            .retain(|_, entry| entry.last_seen.elapsed() < EXPIRE);
+        if self.devices.len() >= MAX_DEVICES {
+            let oldest = self
+                .devices
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_seen)
+                .map(|(addr, _)| addr.clone());
+            if let Some(oldest) = oldest {
+                self.devices.remove(&oldest);
+            }
+        }
+        self.devices.insert(
+            adv.addr.clone(),
+            Entry {
+                adv: adv.clone(),
+                last_seen: now,
+            },
+        );
+    }
+
+    /// 按信号强度从强到弱排列
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut entries: Vec<&Entry> = self
+            .devices
+            .values()
+            .filter(|entry| entry.last_seen.elapsed() < EXPIRE)
+            .collect();
+        entries.sort_by_key(|entry| -entry.adv.rssi);
+        let devices: Vec<serde_json::Value> = entries
+            .into_iter()
+            .map(|entry| {
+                let (manufacturer, data) = match &entry.adv.manufacturer {
+                    Some((id, payload)) => (
+                        Some(*id),
+                        Some(
+                            payload
+                                .iter()
+                                .map(|x| format!("{:02x}", x))
+                                .collect::<String>(),
+                        ),
+                    ),
+                    None => (None, None),
+                };
+                serde_json::json!({
+                    "addr": entry.adv.addr,
+                    "name": entry.adv.name,
+                    "rssi": entry.adv.rssi,
+                    "last_seen_s": entry.last_seen.elapsed().as_secs(),
+                    "manufacturer": manufacturer,
+                    "data": data,
+                })
+            })
+            .collect();
+        serde_json::json!({ "devices": devices })
+    }
+}
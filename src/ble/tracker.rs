@@ -0,0 +1,146 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct PresenceConfig {
+    /// 连续这么多次扫描未发现即认为离开，0 表示不使用该条件
+    pub away_scans: u32,
+    /// 超过这么多秒未发现即认为离开，0 表示不使用该条件
+    pub away_s: u32,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            away_scans: 3,
+            away_s: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceEvent {
+    /// 第一个目标设备到达
+    Arrived,
+    /// 最后一个目标设备离开
+    Departed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DeviceState {
+    last_seen: Instant,
+    missed: u32,
+    present: bool,
+}
+
+/// 按规则记录每个目标设备最近一次被发现的时间，离开需要满足宽限条件，避免属性 7.3 抖动
+pub struct PresenceTracker {
+    config: PresenceConfig,
+    devices: HashMap<usize, DeviceState>,
+    present: bool,
+    events: Vec<PresenceEvent>,
+}
+
+impl PresenceTracker {
+    pub fn new() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<PresenceConfig>()?.unwrap_or_default();
+        log::info!("Presence config: {:?}", config);
+        Ok(Self {
+            config,
+            devices: HashMap::new(),
+            present: false,
+            events: vec![],
+        })
+    }
+
+    pub fn config(&self) -> PresenceConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: PresenceConfig) -> anyhow::Result<()> {
+        crate::nvs::save(config)?;
+        log::info!("Presence config: {:?}", config);
+        self.config = config;
+        Ok(())
+    }
+
+    /// 规则变化后规则序号不再对应原来的设备，在场状态从头开始判断，不上报离开事件
+    pub fn reset(&mut self) {
+        self.devices.clear();
+        self.present = false;
+        self.events.clear();
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.present
+    }
+
+    /// 每个目标设备距最近一次被发现的时间，键为规则序号
+    pub fn last_seen(&self) -> Vec<(usize, Duration, bool)> {
+        let mut devices: Vec<_> = self
+            .devices
+            .iter()
+            .map(|(index, state)| (*index, state.last_seen.elapsed(), state.present))
+            .collect();
+        devices.sort_by_key(|(index, _, _)| *index);
+        devices
+    }
+
+    fn is_away(&self, state: &DeviceState) -> bool {
+        let PresenceConfig { away_scans, away_s } = self.config;
+        if away_scans == 0 && away_s == 0 {
+            return state.missed > 0;
+        }
+        (away_scans > 0 && state.missed >= away_scans)
+            || (away_s > 0 && state.last_seen.elapsed() >= Duration::from_secs(away_s as u64))
+    }
+
+    /// 一次扫描结束，`matched` 为本次扫描匹配到的规则序号
+    pub fn update(&mut self, matched: &HashSet<usize>) {
+        let now = Instant::now();
+        for index in matched {
+            let state = self.devices.entry(*index).or_insert(DeviceState {
+                last_seen: now,
+                missed: 0,
+                present: false,
+            });
+            state.last_seen = now;
+            state.missed = 0;
+            if !state.present {
+                log::info!("Presence rule {} arrived", index);
+                state.present = true;
+            }
+        }
+        let missing: Vec<usize> = self
+            .devices
+            .keys()
+            .filter(|index| !matched.contains(index))
+            .copied()
+            .collect();
+        for index in missing {
+            let mut state = self.devices[&index];
+            state.missed += 1;
+            if state.present && self.is_away(&state) {
+                log::info!("Presence rule {} departed", index);
+                state.present = false;
+            }
+            self.devices.insert(index, state);
+        }
+
+        let present = self.devices.values().any(|state| state.present);
+        if present != self.present {
+            self.present = present;
+            self.events.push(if present {
+                PresenceEvent::Arrived
+            } else {
+                PresenceEvent::Departed
+            });
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<PresenceEvent> {
+        std::mem::take(&mut self.events)
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+// 开灯后等待灯光稳定再学习灯光带来的照度增量
+const LEARN_DELAY: Duration = Duration::from_secs(5);
+// 新的测量值在学习结果中所占的权重
+const LEARN_WEIGHT: f32 = 0.3;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct DaylightConfig {
+    /// 环境光持续低于该值时开灯，单位 lux
+    pub on_below: f32,
+    /// 环境光持续高于该值时关灯，单位 lux，需大于 on_below 形成回差
+    pub off_above: f32,
+    /// 条件需要持续的时间，单位 s
+    pub dwell_s: u32,
+    /// 两次自动开关之间的最短间隔，单位 s
+    pub min_hold_s: u32,
+}
+
+impl Default for DaylightConfig {
+    fn default() -> Self {
+        Self {
+            on_below: 50.0,
+            off_above: 300.0,
+            dwell_s: 60,
+            min_hold_s: 5 * 60,
+        }
+    }
+}
+
+impl DaylightConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.on_below < 0.0 || self.off_above <= self.on_below {
+            anyhow::bail!("off_above must be greater than on_below");
+        }
+        Ok(())
+    }
+}
+
+/// 学习到的灯光本身带来的照度增量，单位 lux
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default)]
+struct LampOffset(f32);
+
+/// 光照自动化（属性 8.2）：天黑开灯，房间足够亮时关灯
+///
+/// 开灯后传感器读到的照度包含灯光本身，关灯判断使用扣除学习增量后的环境光。
+pub struct DaylightController {
+    enabled: bool,
+    config: DaylightConfig,
+    offset: LampOffset,
+    lamp_on: Option<bool>,
+    // 条件开始满足的时间
+    since: Option<Instant>,
+    last_switch: Option<Instant>,
+    // 开灯前的照度和开灯时间
+    learning: Option<(f32, Instant)>,
+    last_lux: Option<f32>,
+}
+
+impl DaylightController {
+    pub fn new() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<DaylightConfig>()?.unwrap_or_default();
+        let offset = crate::nvs::load::<LampOffset>()?.unwrap_or_default();
+        log::info!("Daylight config: {:?}, lamp offset: {:?}", config, offset);
+        Ok(Self {
+            enabled: false,
+            config,
+            offset,
+            lamp_on: None,
+            since: None,
+            last_switch: None,
+            learning: None,
+            last_lux: None,
+        })
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        log::info!("Daylight automation: {}", enabled);
+        self.enabled = enabled;
+        self.since = None;
+    }
+
+    pub fn config(&self) -> DaylightConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: DaylightConfig) -> anyhow::Result<()> {
+        config.validate()?;
+        crate::nvs::save(config)?;
+        log::info!("Daylight config: {:?}", config);
+        self.config = config;
+        self.since = None;
+        Ok(())
+    }
+
+    pub fn lamp_offset(&self) -> f32 {
+        self.offset.0
+    }
+
+    /// 扣除灯光后的环境光估计值
+    pub fn ambient(&self, lux: f32, lamp_on: bool) -> f32 {
+        if lamp_on {
+            (lux - self.offset.0).max(0.0)
+        } else {
+            lux
+        }
+    }
+
+    fn learn(&mut self, lux: f32, lamp_on: bool) {
+        if self.lamp_on == Some(false) && lamp_on {
+            if let Some(before) = self.last_lux {
+                self.learning = Some((before, Instant::now()));
+            }
+        }
+        if !lamp_on {
+            self.learning = None;
+        }
+        let Some((before, at)) = self.learning else {
+            return;
+        };
+        if at.elapsed() < LEARN_DELAY {
+            return;
+        }
+        self.learning = None;
+        let sample = (lux - before).max(0.0);
+        let offset = if self.offset.0 == 0.0 {
+            sample
+        } else {
+            self.offset.0 + (sample - self.offset.0) * LEARN_WEIGHT
+        };
+        self.offset = LampOffset(offset);
+        log::info!("Learned lamp offset: {} lux", offset);
+        if let Err(e) = crate::nvs::save(self.offset) {
+            log::error!("Failed to save lamp offset: {:?}", e);
+        }
+    }
+
+    /// 每次得到新的照度后调用，返回需要切换到的开关状态
+    pub fn update(&mut self, lux: f32, lamp_on: bool) -> Option<bool> {
+        if self.lamp_on.is_some_and(|x| x != lamp_on) {
+            // 无论是手动还是自动切换，都重新计时
+            self.last_switch = Some(Instant::now());
+            self.since = None;
+        }
+        self.learn(lux, lamp_on);
+        self.lamp_on = Some(lamp_on);
+        if self.learning.is_none() {
+            self.last_lux = Some(lux);
+        }
+
+        if !self.enabled {
+            return None;
+        }
+        if self
+            .last_switch
+            .is_some_and(|at| at.elapsed() < Duration::from_secs(self.config.min_hold_s as u64))
+        {
+            return None;
+        }
+        let ambient = self.ambient(lux, lamp_on);
+        let triggered = if lamp_on {
+            ambient > self.config.off_above
+        } else {
+            ambient < self.config.on_below
+        };
+        if !triggered {
+            self.since = None;
+            return None;
+        }
+        let since = *self.since.get_or_insert_with(Instant::now);
+        if since.elapsed() < Duration::from_secs(self.config.dwell_s as u64) {
+            return None;
+        }
+        self.since = None;
+        log::info!(
+            "Daylight automation: ambient {} lux, switch {}",
+            ambient,
+            !lamp_on
+        );
+        Some(!lamp_on)
+    }
+}
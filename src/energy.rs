@@ -0,0 +1,140 @@
+use std::time::{Duration, Instant};
+
+// 每隔一段时间将累计电量写入 nvs，避免频繁擦写 flash
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// 属性 4.2 的最大值，单位 W
+const MAX_POWER: u16 = 10000;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct EnergyConfig {
+    /// 每路灯具的额定功率，单位 W
+    pub wattage: u16,
+    /// 对应属性 4.3：true 为累加形式，false 为每个周期清零
+    pub accumulate: bool,
+    /// 非累加形式下的清零周期，单位 s
+    pub period: u64,
+}
+
+impl Default for EnergyConfig {
+    fn default() -> Self {
+        Self {
+            wattage: 10,
+            accumulate: true,
+            period: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+struct Checkpoint {
+    /// 已累计的电量，单位 Wh
+    consumption: f64,
+    /// 当前周期已经过的时间，单位 s
+    elapsed: f64,
+}
+
+pub struct EnergyMeter {
+    config: EnergyConfig,
+    checkpoint: Checkpoint,
+    lamps_on: u16,
+    last_update: Instant,
+    last_checkpoint: Instant,
+}
+
+impl EnergyMeter {
+    pub fn new() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<EnergyConfig>()?.unwrap_or_default();
+        let checkpoint = crate::nvs::load::<Checkpoint>()?.unwrap_or_default();
+        log::info!("Energy config: {:?}, checkpoint: {:?}", config, checkpoint);
+        let now = Instant::now();
+        Ok(Self {
+            config,
+            checkpoint,
+            lamps_on: 0,
+            last_update: now,
+            last_checkpoint: now,
+        })
+    }
+
+    pub fn config(&self) -> EnergyConfig {
+        self.config.clone()
+    }
+
+    pub fn set_config(&mut self, config: EnergyConfig) -> anyhow::Result<()> {
+        if config.period == 0 {
+            anyhow::bail!("period must be positive");
+        }
+        // 属性 4.2 的上限为 10000 W，所有灯具同时打开也不能超过
+        if config.wattage > MAX_POWER / crate::switch::MAX_CHANNELS as u16 {
+            anyhow::bail!(
+                "wattage must not exceed {}",
+                MAX_POWER / crate::switch::MAX_CHANNELS as u16
+            );
+        }
+        crate::nvs::save(config.clone())?;
+        log::info!("Energy config: {:?}", config);
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn accumulate(&self) -> bool {
+        self.config.accumulate
+    }
+
+    /// 米家修改属性 4.3 时调用，切换形式后从零开始统计
+    pub fn set_accumulate(&mut self, accumulate: bool) -> anyhow::Result<()> {
+        if accumulate == self.config.accumulate {
+            return Ok(());
+        }
+        self.set_config(EnergyConfig {
+            accumulate,
+            ..self.config.clone()
+        })?;
+        self.checkpoint = Checkpoint::default();
+        self.save(Instant::now())
+    }
+
+    /// 当前电功率，单位 W
+    pub fn power(&self) -> u16 {
+        self.config
+            .wattage
+            .saturating_mul(self.lamps_on)
+            .min(MAX_POWER)
+    }
+
+    /// 耗电量，单位 kWh，保留两位小数
+    pub fn consumption(&self) -> f32 {
+        (self.checkpoint.consumption / 10.0).round() as f32 / 100.0
+    }
+
+    /// 按上一次的开关状态对功率积分，再记录当前打开的灯具数量
+    pub fn update(&mut self, lamps_on: u16) -> anyhow::Result<()> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+
+        self.checkpoint.consumption += self.power() as f64 * elapsed / 3600.0;
+        self.checkpoint.elapsed += elapsed;
+        let changed = lamps_on != self.lamps_on;
+        self.lamps_on = lamps_on;
+
+        if !self.config.accumulate && self.checkpoint.elapsed >= self.config.period as f64 {
+            log::info!(
+                "Power consumption period finished: {:.3} Wh",
+                self.checkpoint.consumption
+            );
+            self.checkpoint = Checkpoint::default();
+            return self.save(now);
+        }
+
+        if changed || now.duration_since(self.last_checkpoint) >= CHECKPOINT_INTERVAL {
+            return self.save(now);
+        }
+        Ok(())
+    }
+
+    fn save(&mut self, now: Instant) -> anyhow::Result<()> {
+        self.last_checkpoint = now;
+        crate::nvs::save(self.checkpoint.clone())
+    }
+}
@@ -0,0 +1,133 @@
+use esp_idf_svc::sys::{self, esp};
+use std::time::{Duration, Instant};
+
+// 芯片内部温度超过该值时认为过温，降到恢复温度以下后自动解除
+const OVER_TEMPERATURE: f32 = 80.0;
+const RECOVER_TEMPERATURE: f32 = 70.0;
+// 舵机连续出错次数
+const MAX_SERVO_ERRORS: u32 = 3;
+// 过载或灯具无响应故障的锁定时间，到期后自动解除并允许重试，也可以通过本地接口提前解除
+const OVERLOAD_HOLD: Duration = Duration::from_secs(5 * 60);
+
+/// 对应属性 2.3 的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fault {
+    #[default]
+    None = 0,
+    OverTemperature = 1,
+    Overload = 2,
+    /// 多次按压后光线传感器仍未检测到灯具状态变化
+    LampNotResponding = 3,
+}
+
+#[derive(Default)]
+pub struct FaultMonitor {
+    fault: Fault,
+    latched_at: Option<Instant>,
+    servo_errors: u32,
+}
+
+impl FaultMonitor {
+    pub fn fault(&self) -> Fault {
+        self.fault
+    }
+
+    /// 故障锁定期间禁止驱动舵机
+    pub fn is_latched(&self) -> bool {
+        self.fault != Fault::None
+    }
+
+    fn latch(&mut self, fault: Fault) {
+        if self.fault != fault {
+            log::error!("Fault latched: {:?}", fault);
+        }
+        self.fault = fault;
+        self.latched_at = Some(Instant::now());
+    }
+
+    pub fn clear(&mut self) {
+        if self.fault != Fault::None {
+            log::info!("Fault cleared: {:?}", self.fault);
+        }
+        self.fault = Fault::None;
+        self.latched_at = None;
+        self.servo_errors = 0;
+    }
+
+    /// 手动解除过载或灯具无响应故障，过温故障只能等温度恢复后自动解除
+    pub fn reset(&mut self) -> anyhow::Result<()> {
+        if self.fault == Fault::OverTemperature {
+            anyhow::bail!("Over temperature fault clears when the chip cools down");
+        }
+        self.clear();
+        Ok(())
+    }
+
+    pub fn on_servo_error(&mut self) {
+        self.servo_errors += 1;
+        if self.servo_errors >= MAX_SERVO_ERRORS {
+            self.latch(Fault::Overload);
+        }
+    }
+
+    pub fn on_actuated(&mut self) {
+        self.servo_errors = 0;
+    }
+
+    /// 光线传感器多次重试后仍未确认开关
+    pub fn on_unverified(&mut self) {
+        self.latch(Fault::LampNotResponding);
+    }
+
+    pub fn on_temperature(&mut self, celsius: f32) {
+        match self.fault {
+            Fault::OverTemperature if celsius < RECOVER_TEMPERATURE => self.clear(),
+            _ if celsius > OVER_TEMPERATURE => self.latch(Fault::OverTemperature),
+            _ => {}
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if matches!(self.fault, Fault::Overload | Fault::LampNotResponding)
+            && self
+                .latched_at
+                .is_some_and(|at| at.elapsed() >= OVERLOAD_HOLD)
+        {
+            self.clear();
+        }
+    }
+}
+
+pub struct TemperatureSensor {
+    handle: sys::temperature_sensor_handle_t,
+}
+
+impl TemperatureSensor {
+    pub fn new() -> anyhow::Result<Self> {
+        let config = sys::temperature_sensor_config_t {
+            range_min: 20,
+            range_max: 100,
+            clk_src:
+                sys::soc_periph_temperature_sensor_clk_src_t_TEMPERATURE_SENSOR_CLK_SRC_DEFAULT,
+        };
+        let mut handle = std::ptr::null_mut();
+        esp!(unsafe { sys::temperature_sensor_install(&config, &mut handle) })?;
+        esp!(unsafe { sys::temperature_sensor_enable(handle) })?;
+        Ok(Self { handle })
+    }
+
+    pub fn read(&self) -> anyhow::Result<f32> {
+        let mut celsius = 0.0;
+        esp!(unsafe { sys::temperature_sensor_get_celsius(self.handle, &mut celsius) })?;
+        Ok(celsius)
+    }
+}
+
+impl Drop for TemperatureSensor {
+    fn drop(&mut self) {
+        unsafe {
+            sys::temperature_sensor_disable(self.handle);
+            sys::temperature_sensor_uninstall(self.handle);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    SingleTap,
+    DoubleTap,
+    /// 手掌遮住传感器超过一定时间
+    LongCover,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GestureAction {
+    None,
+    /// 切换第一路开关
+    Toggle,
+    /// 上报事件 3.1
+    Click,
+    /// 一段时间后关闭第一路开关
+    OffTimer,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct GestureConfig {
+    pub single_tap: GestureAction,
+    pub double_tap: GestureAction,
+    pub long_cover: GestureAction,
+    /// 两次点击之间的最长间隔，单位 ms
+    pub double_tap_ms: u32,
+    /// 长时间遮挡的判定时间，单位 ms
+    pub long_cover_ms: u32,
+    /// 延时关闭的时间，单位 s
+    pub off_timer_s: u32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            single_tap: GestureAction::Toggle,
+            double_tap: GestureAction::Click,
+            long_cover: GestureAction::OffTimer,
+            double_tap_ms: 1000,
+            long_cover_ms: 3000,
+            off_timer_s: 10 * 60,
+        }
+    }
+}
+
+impl GestureConfig {
+    pub fn load() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<GestureConfig>()?.unwrap_or_default();
+        log::info!("Gesture config: {:?}", config);
+        Ok(config)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        crate::nvs::save(*self)?;
+        log::info!("Gesture config: {:?}", self);
+        Ok(())
+    }
+
+    pub fn action(&self, gesture: Gesture) -> GestureAction {
+        match gesture {
+            Gesture::SingleTap => self.single_tap,
+            Gesture::DoubleTap => self.double_tap,
+            Gesture::LongCover => self.long_cover,
+        }
+    }
+}
+
+/// 根据触摸检测器给出的遮挡状态识别手势
+///
+/// 单击需要等待双击间隔结束才能确定，未配置双击动作时立即触发。
+#[derive(Default)]
+pub struct GestureRecognizer {
+    pressed_at: Option<Instant>,
+    long_reported: bool,
+    last_tap: Option<Instant>,
+}
+
+impl GestureRecognizer {
+    /// 每次采样后调用，`touching` 为当前是否被遮挡
+    pub fn update(&mut self, touching: bool, config: &GestureConfig) -> Option<Gesture> {
+        let now = Instant::now();
+        match (self.pressed_at, touching) {
+            (None, true) => {
+                self.pressed_at = Some(now);
+                self.long_reported = false;
+                None
+            }
+            (Some(at), true) => {
+                if !self.long_reported
+                    && now - at >= Duration::from_millis(config.long_cover_ms as u64)
+                {
+                    self.long_reported = true;
+                    self.last_tap = None;
+                    return Some(Gesture::LongCover);
+                }
+                None
+            }
+            (Some(_), false) => {
+                self.pressed_at = None;
+                if self.long_reported {
+                    return None;
+                }
+                if self.last_tap.take().is_some() {
+                    return Some(Gesture::DoubleTap);
+                }
+                if config.double_tap == GestureAction::None {
+                    return Some(Gesture::SingleTap);
+                }
+                self.last_tap = Some(now);
+                None
+            }
+            (None, false) => {
+                let window = Duration::from_millis(config.double_tap_ms as u64);
+                match self.last_tap {
+                    Some(at) if now - at >= window => {
+                        self.last_tap = None;
+                        Some(Gesture::SingleTap)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use esp_idf_hal::{
+    gpio::{AnyOutputPin, Output, PinDriver},
+    ledc::LedcDriver,
+};
+use std::time::Instant;
+
+use crate::net::NetState;
+
+// 亮度百分比，PWM 模式下关灯时保持微亮便于夜间寻找开关
+const FULL: u32 = 100;
+const DIM: u32 = 5;
+
+// 已被舵机、串口、光线传感器、USB 和 flash 占用的引脚
+const RESERVED_PINS: [u8; 8] = [1, 9, 10, 11, 12, 13, 19, 20];
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct IndicatorConfig {
+    /// 指示灯的 GPIO 编号，修改后重启生效
+    pub pin: u8,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self { pin: 2 }
+    }
+}
+
+impl IndicatorConfig {
+    pub fn load() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<IndicatorConfig>()?.unwrap_or_default();
+        log::info!("Indicator config: {:?}", config);
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        // ESP32-S3 可用作输出的引脚为 0-21 和 38-48，33-37 在带 Octal PSRAM 的模组上被占用
+        let valid = matches!(self.pin, 0..=21 | 38..=48);
+        if !valid || RESERVED_PINS.contains(&self.pin) {
+            anyhow::bail!("GPIO {} cannot be used for the indicator", self.pin);
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        self.validate()?;
+        crate::nvs::save(*self)?;
+        log::info!("Indicator config: {:?}, restart to apply", self);
+        Ok(())
+    }
+
+    pub fn output_pin(&self) -> anyhow::Result<AnyOutputPin> {
+        self.validate()?;
+        // 引脚编号已校验，且没有被其他外设使用
+        Ok(unsafe { AnyOutputPin::new(self.pin as i32) })
+    }
+}
+
+pub enum IndicatorOutput {
+    Gpio(PinDriver<'static, AnyOutputPin, Output>),
+    Pwm(LedcDriver<'static>),
+}
+
+impl IndicatorOutput {
+    fn set(&mut self, level: u32) -> anyhow::Result<()> {
+        match self {
+            IndicatorOutput::Gpio(pin) => {
+                if level >= FULL {
+                    pin.set_high()?
+                } else {
+                    pin.set_low()?
+                }
+            }
+            IndicatorOutput::Pwm(driver) => {
+                driver.set_duty(driver.get_max_duty() * level / 100)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 指示灯（服务 5）
+///
+/// - 故障：快闪
+/// - 配网中：慢闪
+/// - 连接网络中：每 2 秒闪一下
+/// - 在线：常亮，开关打开时全亮，关闭时微亮（GPIO 模式下熄灭）
+pub struct Indicator {
+    output: IndicatorOutput,
+    level: Option<u32>,
+    started: Instant,
+}
+
+impl Indicator {
+    pub fn new(output: IndicatorOutput) -> Self {
+        Self {
+            output,
+            level: None,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, enabled: bool, switch_on: bool, fault: bool, net: NetState) {
+        let ms = self.started.elapsed().as_millis();
+        let level = if !enabled {
+            0
+        } else if fault {
+            if ms / 400 % 2 == 0 {
+                FULL
+            } else {
+                0
+            }
+        } else {
+            match net {
+                NetState::Provisioning => {
+                    if ms / 1000 % 2 == 0 {
+                        FULL
+                    } else {
+                        0
+                    }
+                }
+                NetState::Connecting => {
+                    if ms % 2000 < 500 {
+                        FULL
+                    } else {
+                        0
+                    }
+                }
+                NetState::Online => {
+                    if switch_on {
+                        FULL
+                    } else {
+                        DIM
+                    }
+                }
+            }
+        };
+        if self.level != Some(level) {
+            match self.output.set(level) {
+                Ok(_) => self.level = Some(level),
+                Err(e) => log::error!("Failed to set indicator: {:?}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// 属性 8.1 的取值范围
+const MAX_LUX: f32 = 10000.0;
+// 分段线性表最多保存的参考点数量
+const MAX_POINTS: usize = 8;
+
+/// 将 ADC 读数（mV）换算为照度（lux）的校准曲线
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum LuxCurve {
+    /// 光敏电阻接地、固定电阻接电源的分压电路，读数越高越暗
+    Divider {
+        /// 分压电路的供电电压，单位 mV
+        supply_mv: f32,
+        /// 固定电阻阻值，单位 Ω
+        fixed_ohm: f32,
+        /// 光敏电阻在 10 lux 下的阻值，单位 Ω
+        r10_ohm: f32,
+        /// 光敏电阻的 gamma 值
+        gamma: f32,
+    },
+    /// 由参考点 (ADC 读数, lux) 组成的分段线性表，按读数升序排列
+    Table(Vec<(u16, f32)>),
+}
+
+impl Default for LuxCurve {
+    fn default() -> Self {
+        // GL5528 光敏电阻与 10kΩ 电阻分压
+        LuxCurve::Divider {
+            supply_mv: 3300.0,
+            fixed_ohm: 10000.0,
+            r10_ohm: 15000.0,
+            gamma: 0.7,
+        }
+    }
+}
+
+impl LuxCurve {
+    fn convert(&self, raw: u16) -> f32 {
+        match self {
+            LuxCurve::Divider {
+                supply_mv,
+                fixed_ohm,
+                r10_ohm,
+                gamma,
+            } => {
+                let mv = (raw as f32).min(supply_mv - 1.0);
+                let resistance = fixed_ohm * mv / (supply_mv - mv);
+                if resistance <= 0.0 {
+                    return MAX_LUX;
+                }
+                10.0 * (r10_ohm / resistance).powf(1.0 / gamma)
+            }
+            LuxCurve::Table(points) => match points.as_slice() {
+                // 参考点不足两个时无法插值，仍使用默认的分压模型
+                [] | [_] => LuxCurve::default().convert(raw),
+                _ => {
+                    // 超出表格范围时按两端的线段外推
+                    let i = points
+                        .windows(2)
+                        .position(|w| raw <= w[1].0)
+                        .unwrap_or(points.len() - 2);
+                    let ((x0, y0), (x1, y1)) = (points[i], points[i + 1]);
+                    let t = (raw as f32 - x0 as f32) / (x1 as f32 - x0 as f32);
+                    y0 + (y1 - y0) * t
+                }
+            },
+        }
+    }
+}
+
+/// 光照换算，校准曲线保存在 nvs 中
+pub struct LuxConverter {
+    curve: LuxCurve,
+}
+
+impl LuxConverter {
+    pub fn new() -> anyhow::Result<Self> {
+        let curve = crate::nvs::load::<LuxCurve>()?.unwrap_or_default();
+        log::info!("Lux curve: {:?}", curve);
+        Ok(Self { curve })
+    }
+
+    pub fn curve(&self) -> &LuxCurve {
+        &self.curve
+    }
+
+    pub fn set_curve(&mut self, curve: LuxCurve) -> anyhow::Result<()> {
+        crate::nvs::save(curve.clone())?;
+        log::info!("Lux curve: {:?}", curve);
+        self.curve = curve;
+        Ok(())
+    }
+
+    /// 换算为 lux，保留一位小数
+    pub fn lux(&self, raw: u16) -> f32 {
+        let lux = self.curve.convert(raw).clamp(0.0, MAX_LUX);
+        (lux * 10.0).round() / 10.0
+    }
+
+    /// 用照度计测得的参考值校准：记录当前读数对应的 lux，并切换到分段线性表
+    ///
+    /// 读数相近的旧参考点会被替换，参考点越多曲线越准确，至少需要两个。
+    pub fn add_reference(&mut self, raw: u16, lux: f32) -> anyhow::Result<()> {
+        if !(0.0..=MAX_LUX).contains(&lux) {
+            anyhow::bail!("Lux must be between 0 and {}", MAX_LUX);
+        }
+        let mut points = match &self.curve {
+            LuxCurve::Table(points) => points.clone(),
+            LuxCurve::Divider { .. } => vec![],
+        };
+        points.retain(|(x, _)| x.abs_diff(raw) > 20);
+        points.push((raw, lux));
+        points.sort_by_key(|(x, _)| *x);
+        if points.len() > MAX_POINTS {
+            anyhow::bail!("Too many reference points, reset the calibration first");
+        }
+        self.set_curve(LuxCurve::Table(points))
+    }
+
+    pub fn reset(&mut self) -> anyhow::Result<()> {
+        crate::nvs::remove::<LuxCurve>()?;
+        self.curve = LuxCurve::default();
+        log::info!("Lux curve reset: {:?}", self.curve);
+        Ok(())
+    }
+}
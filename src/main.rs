@@ -1,36 +1,42 @@
-use esp32_nimble::{BLEDevice, BLEScan};
+use esp_idf_hal::adc::oneshot::AdcDriver;
 use esp_idf_hal::{
     adc::{
         attenuation::DB_11,
-        oneshot::{
-            config::AdcChannelConfig,
-            AdcChannelDriver
-        }
-    }, 
-    ledc::{
-        config::TimerConfig,
-        LedcDriver,
-        LedcTimerDriver,
-        Resolution
+        oneshot::{config::AdcChannelConfig, AdcChannelDriver},
     },
+    ledc::{config::TimerConfig, LedcDriver, LedcTimerDriver, Resolution},
     peripherals::Peripherals,
-    task::block_on,
-    prelude::*
+    prelude::*,
 };
 use esp_idf_svc::log::set_target_level;
-use parser::{json_str_to_vec, Value};
-use std::{collections::HashSet, sync::{Arc, Mutex}, thread::{self, spawn}, time::Duration};
-use ap::status;
-use esp_idf_hal::adc::oneshot::AdcDriver;
+use parser::Value;
+use settings::Setting;
+use smart_light_core::{sampling, touch};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, spawn},
+    time::{Duration, Instant},
+};
 
+mod actuator;
 mod ap;
+mod autoswitch;
+mod ble;
+mod daylight;
+mod energy;
+mod fault;
+mod gesture;
+mod indicator;
+mod lux;
 mod miio;
 mod net;
 mod nvs;
 mod parser;
 mod serial;
-
-const MOTOR: (f32, f32) = (0.028, 0.053);
+mod settings;
+mod switch;
+mod verifier;
 
 fn main() -> anyhow::Result<()> {
     std::thread::sleep(Duration::from_secs(5));
@@ -48,25 +54,80 @@ fn main() -> anyhow::Result<()> {
     let modem = peripherals.modem;
 
     let timer_driver = LedcTimerDriver::new(
-        peripherals.ledc.timer1, 
+        peripherals.ledc.timer1,
         &TimerConfig::default()
             .frequency(50.Hz().into())
-            .resolution(Resolution::Bits13)
+            .resolution(Resolution::Bits13),
     )?;
 
     let mut miio = crate::miio::IoTFramework::new(
-        peripherals.uart1, 
-        pins.gpio12, 
+        peripherals.uart1,
+        pins.gpio12,
         pins.gpio11,
         "csbupt.switch.smsw",
         "0001",
-        "24351"
+        "24351",
     )?;
 
-    let mut driver = LedcDriver::new(peripherals.ledc.channel0, timer_driver, pins.gpio9)?;
-    let max_duty = driver.get_max_duty();
+    let gangs = nvs::load::<switch::GangConfig>()?.unwrap_or_default();
+    let channels = gangs.channels.clamp(1, switch::MAX_CHANNELS);
+    log::info!("Switch channels: {}", channels);
 
-    driver.set_duty((max_duty as f32 * MOTOR.1) as u32)?;
+    // 每一路开关的舵机: gpio9, gpio10, gpio13
+    let mut drivers = vec![LedcDriver::new(
+        peripherals.ledc.channel0,
+        &timer_driver,
+        pins.gpio9,
+    )?];
+    if channels >= 2 {
+        drivers.push(LedcDriver::new(
+            peripherals.ledc.channel2,
+            &timer_driver,
+            pins.gpio10,
+        )?);
+    }
+    if channels >= 3 {
+        drivers.push(LedcDriver::new(
+            peripherals.ledc.channel3,
+            &timer_driver,
+            pins.gpio13,
+        )?);
+    }
+
+    let last_close_time = Arc::new(Mutex::new(None::<u64>));
+
+    let mut switches = vec![];
+    for (index, driver) in drivers.into_iter().enumerate() {
+        let servo = actuator::ServoActuator::new(driver, &format!("ch{}", index))?;
+        switches.push(switch::SwitchChannel::new(
+            switch::siid(index),
+            servo,
+            verifier::ActuationVerifier::new(index)?,
+            Arc::clone(&last_close_time),
+        )?);
+    }
+
+    let indicator_pin = indicator::IndicatorConfig::load()?
+        .output_pin()
+        .or_else(|e| {
+            log::error!("Invalid indicator pin, fall back to gpio2: {:?}", e);
+            indicator::IndicatorConfig::default().output_pin()
+        })?;
+    #[cfg(not(feature = "indicator_pwm"))]
+    let indicator_output =
+        indicator::IndicatorOutput::Gpio(esp_idf_hal::gpio::PinDriver::output(indicator_pin)?);
+    #[cfg(feature = "indicator_pwm")]
+    let indicator_output = indicator::IndicatorOutput::Pwm(LedcDriver::new(
+        peripherals.ledc.channel1,
+        LedcTimerDriver::new(
+            peripherals.ledc.timer0,
+            &TimerConfig::default()
+                .frequency(1.kHz().into())
+                .resolution(Resolution::Bits10),
+        )?,
+        indicator_pin,
+    )?);
+    let mut indicator = indicator::Indicator::new(indicator_output);
 
     let ble_device_cnt = Arc::new(Mutex::new(None::<usize>));
     let ble_device_cnt_clone = Arc::clone(&ble_device_cnt);
@@ -74,185 +135,418 @@ fn main() -> anyhow::Result<()> {
     let wifi_sta_cnt = Arc::new(Mutex::new(None::<u32>));
     let wifi_sta_cnt_clone = Arc::clone(&wifi_sta_cnt);
 
+    let wlan_enabled = Arc::new((Mutex::new(true), Condvar::new()));
+    let wlan_enabled_clone = Arc::clone(&wlan_enabled);
+
+    let energy = Arc::new(Mutex::new(energy::EnergyMeter::new()?));
+    let energy_clone = Arc::clone(&energy);
+
     let illumination = Arc::new(Mutex::new(None::<u16>));
     let illumination_clone = Arc::clone(&illumination);
 
-    let bluetooth_devices = Arc::new(Mutex::<std::vec::Vec<String>>::new(vec![]));
-    let bluetooth_devices_clone = Arc::clone(&bluetooth_devices);
+    let lux = Arc::new(Mutex::new(lux::LuxConverter::new()?));
+    let gesture_config = Arc::new(Mutex::new(gesture::GestureConfig::load()?));
+    let daylight = Arc::new(Mutex::new(daylight::DaylightController::new()?));
+    let daylight_clone = Arc::clone(&daylight);
+    let sampling_config = Arc::new(Mutex::new(sampling::SamplingConfig::load()?));
+    let touch_config = Arc::new(Mutex::new(touch::TouchConfig::load()?));
 
-    let bluetooth_matched = Arc::new(Mutex::new(false));
-    let bluetooth_matched_clone = Arc::clone(&bluetooth_matched);
+    let auto_switch = Arc::new(Mutex::new(autoswitch::AutoSwitch::new()?));
+    let auto_switch_clone = Arc::clone(&auto_switch);
+    let ble_table = Arc::new(Mutex::new(ble::DeviceTable::default()));
+    let identifier = Arc::new(Mutex::new(ble::DeviceIdentifier::new()?));
+    let presence = Arc::new(Mutex::new(ble::PresenceTracker::new()?));
+    let presence_clone = Arc::clone(&presence);
+    let presence_rules_tracker = Arc::clone(&presence);
+    let presence_scanner = Arc::clone(&presence);
 
-    let illumination_touched = Arc::new(Mutex::new(false));
-    let illumination_touched_clone = Arc::clone(&illumination_touched);
+    let presence_rules = Arc::new(Mutex::new(ble::PresenceRules::default()));
+    let presence_rules_clone = Arc::clone(&presence_rules);
 
-    let last_close_time = Arc::new(Mutex::new(None::<u64>));
-    let last_close_time_clone = Arc::clone(&last_close_time);
+    let scan_config = Arc::new(Mutex::new(ble::ScanConfig::load()?));
+    let ble_table_clone = Arc::clone(&ble_table);
+    let identifier_clone = Arc::clone(&identifier);
+    let mut ble_devices = HashSet::new();
+    let mut matched = HashSet::new();
+    let scanner = Arc::new(ble::Scanner::spawn(
+        Arc::clone(&scan_config),
+        move |event| match event {
+            ble::ScanEvent::Found(adv) => {
+                matched.extend(presence_rules.lock().unwrap().matching(&adv));
+                ble_table_clone.lock().unwrap().insert(&adv);
+                ble_devices.insert(identifier_clone.lock().unwrap().identify(&adv));
+            }
+            ble::ScanEvent::Finished => {
+                let cnt = identifier_clone
+                    .lock()
+                    .unwrap()
+                    .record_scan(ble_devices.len());
+                *ble_device_cnt.lock().unwrap() = Some(cnt);
+                presence_scanner.lock().unwrap().update(&matched);
+                ble_devices.clear();
+                matched.clear();
+            }
+            ble::ScanEvent::Aborted => {
+                ble_devices.clear();
+                matched.clear();
+            }
+        },
+    )?);
+
+    let api = net::api::Api {
+        servos: switches.iter().map(|s| Arc::clone(&s.servo)).collect(),
+        faults: switches.iter().map(|s| Arc::clone(&s.faults)).collect(),
+        verify: switches.iter().map(|s| s.verify_config()).collect(),
+        lux: Arc::clone(&lux),
+        energy: Arc::clone(&energy),
+        illumination: Arc::clone(&illumination),
+        gestures: Arc::clone(&gesture_config),
+        daylight: Arc::clone(&daylight),
+        sampling: Arc::clone(&sampling_config),
+        touch: Arc::clone(&touch_config),
+        presence: Arc::clone(&presence),
+        auto_switch: Arc::clone(&auto_switch),
+        ble_devices: Arc::clone(&ble_table),
+        identifier: Arc::clone(&identifier),
+        scan_config: Arc::clone(&scan_config),
+        scan_status: scanner.status(),
+        token: net::ApiToken::load()?,
+    };
+    log::info!("Local API token: {}", api.token.as_str());
+    // 在配网线程之前生成 PIN，蓝牙配网和蓝牙控制使用同一个，只在生成时打印一次
+    ble::GattConfig::load()?;
 
+    let touch_gesture = Arc::new(Mutex::new(None::<gesture::Gesture>));
+    let touch_gesture_clone = Arc::clone(&touch_gesture);
+
+    let gesture_config_clone = Arc::clone(&gesture_config);
+
+    let sampling_config_clone = Arc::clone(&sampling_config);
+
+    let touch_config_clone = Arc::clone(&touch_config);
+    let mut touch_detector = touch::TouchDetector::new(*touch_config.lock().unwrap());
+    let mut gesture_recognizer = gesture::GestureRecognizer::default();
     spawn(move || {
         let adc = AdcDriver::new(peripherals.adc1).unwrap();
-        let mut adc_pin = AdcChannelDriver::new(&adc, pins.gpio1, &AdcChannelConfig {
-            attenuation: DB_11,
-            calibration: true,
-            ..Default::default()
-        }).unwrap();
+        let mut adc_pin = AdcChannelDriver::new(
+            &adc,
+            pins.gpio1,
+            &AdcChannelConfig {
+                attenuation: DB_11,
+                calibration: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let mut pipeline = sampling::SamplingPipeline::new(*sampling_config_clone.lock().unwrap());
+        let mut errors = 0u32;
         loop {
-            thread::sleep(Duration::from_millis(500));
-            match adc.read(&mut adc_pin) {
-                Ok(value) => {
-                    match *illumination.lock().unwrap() {
-                        Some(last_value) => {
-                            if last_value.abs_diff(value) <= 50 {
-                                continue;
-                            }
-                            if value > last_value + 1000 && 
-                                last_close_time.lock().unwrap().map_or(true, |x| x + 2 < std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs()) {
-                                *illumination_touched.lock().unwrap() = true;
-                                log::info!("touched");
-                            }
-                        },
-                        None => {}
+            let config = *sampling_config_clone.lock().unwrap();
+            if config != pipeline.config() {
+                pipeline.set_config(config);
+            }
+            let touch_config = *touch_config_clone.lock().unwrap();
+            if touch_config != touch_detector.config() {
+                touch_detector.set_config(touch_config);
+            }
+            thread::sleep(Duration::from_millis(config.interval_ms as u64));
+
+            let mut readings = vec![];
+            for _ in 0..config.oversample {
+                match adc.read(&mut adc_pin) {
+                    Ok(value) => readings.push(value),
+                    Err(e) => {
+                        errors += 1;
+                        if errors % 100 == 1 {
+                            log::warn!("Failed to read light sensor ({} errors): {:?}", errors, e);
+                        }
+                    }
+                }
+            }
+            match pipeline.push(&readings) {
+                Some(sample) => {
+                    let since_close = last_close_time.lock().unwrap().map(|x| {
+                        let now = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs();
+                        Duration::from_secs(now.saturating_sub(x))
+                    });
+                    if touch_detector.feed(
+                        sample.raw,
+                        Duration::from_millis(config.interval_ms as u64),
+                        since_close,
+                    ) {
+                        log::info!("touched");
+                    }
+                    let config = *gesture_config_clone.lock().unwrap();
+                    if let Some(recognized) =
+                        gesture_recognizer.update(touch_detector.is_touching(), &config)
+                    {
+                        log::info!("Gesture: {:?}", recognized);
+                        *touch_gesture.lock().unwrap() = Some(recognized);
+                    }
+                    let value = sample.filtered;
+                    if illumination
+                        .lock()
+                        .unwrap()
+                        .is_some_and(|last_value| last_value.abs_diff(value) <= 50)
+                    {
+                        continue;
                     }
                     *illumination.lock().unwrap() = Some(value);
                     log::info!("illumination: {}", value);
-                },
-                _ => continue
+                }
+                None => continue,
             }
         }
     });
 
-    thread::Builder::new().stack_size(16 * 1024).spawn(move || {
-        let mut net_manager = net::NetManager::new(modem).unwrap();
+    thread::Builder::new()
+        .stack_size(16 * 1024)
+        .spawn(move || {
+            let mut net_manager = net::NetManager::new(modem).unwrap();
 
-        loop {
-            match net_manager.connect() {
-                Ok(_) => {
-                    log::info!("Connected to the network");
-                    loop {
-                        match status() {
-                            Ok(data) => {
-                                log::info!("AP status: {:?}", data);
-                                *wifi_sta_cnt.lock().unwrap() = Some(data.ap.sta_count.parse::<u32>().unwrap());
-                            }
-                            Err(e) => {
-                                log::error!("Failed to get AP status: {:?}", e);
-                            }
+            loop {
+                // 配网期间停止蓝牙扫描，把射频留给配网，配网结束后恢复
+                let provisioning = net_manager.needs_provisioning().unwrap_or(false);
+                if provisioning {
+                    scanner.stop();
+                }
+                let result = net_manager.connect();
+                if provisioning {
+                    if let Err(e) = scanner.start() {
+                        log::error!("Failed to restart BLE scanner: {:?}", e);
+                    }
+                }
+                match result {
+                    Ok(_) => {
+                        log::info!("Connected to the network");
+                        let _server = net::api::serve(api.clone())
+                            .map_err(|e| log::error!("Failed to start local API server: {:?}", e))
+                            .ok();
+                        // 蓝牙扫描在独立线程中进行，这里只需保持本地接口运行，断线后重新连接
+                        while net_manager.is_connected() {
+                            thread::sleep(Duration::from_secs(10));
                         }
-
-                        std::thread::sleep(Duration::from_secs(10));
-
-                        block_on(async {
-                            // log::info!("Start scanning BLE devices");
-                            let ble_device = BLEDevice::take();
-                            let mut ble_scan = BLEScan::new();
-                            let mut ble_devices = HashSet::new();
-                            let mut matched = false;
-
-                            ble_scan
-                                .start(ble_device, 10000, |device, data| {
-                                    if let Some(name) = data.name() {
-                                        if bluetooth_devices.lock().unwrap().iter().find(|&x| x == name).is_some() {
-                                            matched = true;
-                                        }
-                                    }
-                                    ble_devices.insert(format!("{:?}", device.addr()));
-                                    None::<()>
-                                })
-                                .await
-                                .unwrap();
-                            let cnt = ble_devices.len();
-                            // log::info!("Scanned BLE devices: {:?}", cnt);
-                            // log::info!("Matched devices: {:?}", matched);
-                            *ble_device_cnt.lock().unwrap() = Some(cnt);
-                            *bluetooth_matched.lock().unwrap() = matched;
-                        });
-
+                        log::warn!("Network disconnected, reconnecting");
+                    }
+                    Err(e) => {
+                        log::error!("Failed to connect to the network: {:?}", e);
                         std::thread::sleep(Duration::from_secs(10));
+                        continue;
                     }
                 }
-                Err(e) => {
-                    log::error!("Failed to connect to the network: {:?}", e);
-                    std::thread::sleep(Duration::from_secs(10));
-                    continue;
-                }
             }
-        }
-    })?;
+        })?;
+
+    ap::spawn_poller(wlan_enabled, wifi_sta_cnt)?;
 
     #[cfg(feature = "restore")]
     miio.restore()?;
 
+    let temperature_sensor = fault::TemperatureSensor::new()
+        .map_err(|e| log::error!("Failed to start temperature sensor: {:?}", e))
+        .ok();
+
     miio.registers(vec![
-            (1, 1, "YouXam"),
-            (1, 2, "csbupt.switch.smsw"),
-            (1, 3, "0001"),
-            (1, 4, "0001"),
-        ])
-        .registers(vec![
-            (2, 2, 0), // 模式
-            (2, 3, 0), // 故障
-            (4, 1, 0), // 功耗参数
-            (4, 2, 0), // 电功率
-            (6, 1, 0), // Wifi 设备数量
-            (7, 1, 0), // 蓝牙设备数量
-            (8, 1, 0), // 亮度
-        ])
-        .registers(vec![
-            (2, 4, false), // 防闪烁模式
-            (4, 3, false), // 耗电量使用累加形式
-            (5, 1, false), // 指示灯开关
-            (7, 3, false), // 是否搜索到目标设备
-        ])
-        .register(2, 1, false)  // 开关
-        .on(move |e| if let &Value::Boolean(value) = e {
-            if value {
-                log::info!("Open the switch");
-                driver.set_duty((max_duty as f32 * MOTOR.0) as u32).unwrap();
-            } else {
-                last_close_time_clone.lock().unwrap().replace(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs());
-                log::info!("Close the switch");
-                driver.set_duty((max_duty as f32 * MOTOR.1) as u32).unwrap();
+        (1, 1, "YouXam"),
+        (1, 2, "csbupt.switch.smsw"),
+        (1, 3, "0001"),
+        (1, 4, "0001"),
+    ])
+    .registers(vec![
+        (4, 2, 0), // 电功率
+        (6, 1, 0), // Wifi 设备数量
+        (7, 1, 0), // 蓝牙设备数量
+    ])
+    .register(4, 1, energy_clone.lock().unwrap().consumption()) // 功耗参数
+    .register(4, 3, energy_clone.lock().unwrap().accumulate()) // 耗电量使用累加形式
+    .on(move |e| {
+        if let &Value::Boolean(value) = e {
+            if let Err(e) = energy.lock().unwrap().set_accumulate(value) {
+                log::error!("Failed to set energy accumulation: {:?}", e);
             }
-        })
-        .register(7, 4, "") // 蓝牙设备名称
-        .on(move |value| {
-            match value {
-                Value::String(value) => {
-                    *bluetooth_devices_clone.lock().unwrap() = json_str_to_vec(&value).unwrap();
-                    println!("bluetooth-devices: {}", value)
-                },
-                _ => {}
-                
+        }
+    })
+    .load()?
+    .register(8, 1, 0f32) // 亮度，单位 lux
+    .register(8, 2, false) // 光照自动开关灯
+    .on(move |e| {
+        if let &Value::Boolean(value) = e {
+            daylight.lock().unwrap().set_enabled(value);
+        }
+    })
+    .load()?
+    .register(7, 5, false) // 在场自动开关灯
+    .on(move |e| {
+        if let &Value::Boolean(value) = e {
+            auto_switch.lock().unwrap().set_enabled(value);
+        }
+    })
+    .load()?
+    .registers(vec![
+        (7, 3, false), // 是否搜索到目标设备
+    ])
+    .register(5, 1, true) // 指示灯开关
+    .load()?
+    .register(6, 2, true) // 统计 Wifi 设备数量
+    .on(move |e| {
+        if let &Value::Boolean(value) = e {
+            let (lock, cvar) = &*wlan_enabled_clone;
+            *lock.lock().unwrap() = value;
+            cvar.notify_all();
+        }
+    })
+    .load()?
+    .register(7, 4, "") // 蓝牙在场规则
+    .validate(
+        |value| matches!(value, Value::String(value) if ble::PresenceRules::parse(value).is_ok()),
+    )
+    .on(move |value| match value {
+        Value::String(value) => match ble::PresenceRules::parse(value) {
+            Ok(rules) => {
+                *presence_rules_clone.lock().unwrap() = rules;
+                presence_rules_tracker.lock().unwrap().reset();
+                println!("bluetooth-devices: {}", value)
             }
-        })
-        .load()?;
+            Err(e) => log::error!("Invalid presence rules {}: {:?}", value, e),
+        },
+        _ => {}
+    })
+    .load()?;
 
-    miio.set_property(2, 1, Value::Boolean(true))?;
+    for switch in switches.iter() {
+        switch.register(&mut miio)?;
+    }
+    for switch in switches.iter_mut() {
+        switch.restore(&mut miio)?;
+    }
+
+    let gatt_config = ble::GattConfig::load()?;
+    let mut gatt = if gatt_config.enabled {
+        ble::GattServer::start(&gatt_config)
+            .map_err(|e| log::error!("Failed to start BLE control service: {:?}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    // 长时间遮挡手势设置的延时关闭时间
+    let mut off_timer = None::<Instant>;
 
     #[allow(unused_must_use)]
     loop {
         miio.tick();
 
+        let temperature = temperature_sensor.as_ref().and_then(|s| s.read().ok());
+        let illumination_value = *illumination_clone.lock().unwrap();
+        for switch in switches.iter_mut() {
+            switch.tick(&mut miio, illumination_value, temperature);
+        }
+
+        let (consumption, power) = {
+            let mut energy = energy_clone.lock().unwrap();
+            energy.update(switches.iter().filter(|s| s.state()).count() as u16);
+            (energy.consumption(), energy.power())
+        };
+        miio.set_property(4, 1, Value::Float(consumption));
+        miio.set_property(4, 2, Value::Integer(power as u32));
+
+        indicator.update(
+            miio.get_from_cache(5, 1) == Some(&Value::Boolean(true)),
+            switches.iter().any(|s| s.state()),
+            switches.iter().any(|s| s.is_faulted()),
+            net::state(),
+        );
+
         if let Some(ble_device_cnt_) = *ble_device_cnt_clone.lock().unwrap() {
             miio.set_property(7, 1, Value::Integer(ble_device_cnt_ as u32));
         }
         if let Some(wifi_sta_cnt_) = *wifi_sta_cnt_clone.lock().unwrap() {
             miio.set_property(6, 1, Value::Integer(wifi_sta_cnt_ as u32));
         }
-        if let Some(illumination) = *illumination_clone.lock().unwrap() {
-            miio.set_property(8, 1, Value::Integer(illumination as u32));
+        let lux_ = illumination_clone
+            .lock()
+            .unwrap()
+            .map(|x| lux.lock().unwrap().lux(x));
+        if let Some(lux_) = lux_ {
+            miio.set_property(8, 1, Value::Float(lux_));
+            let command = daylight_clone
+                .lock()
+                .unwrap()
+                .update(lux_, switches[0].lamp_on());
+            if let Some(on) = command {
+                if let Ok(true) = switches[0].switch_locally(&mut miio, on) {
+                    auto_switch_clone.lock().unwrap().note_command(on);
+                }
+            }
+        }
+        let recognized = touch_gesture_clone.lock().unwrap().take();
+        if let Some(recognized) = recognized {
+            let config = *gesture_config.lock().unwrap();
+            match config.action(recognized) {
+                gesture::GestureAction::None => {}
+                gesture::GestureAction::Toggle => {
+                    switches[0].toggle(&mut miio);
+                }
+                gesture::GestureAction::Click => {
+                    if switches[0].local_control_enabled(&miio) {
+                        miio.event_occurred(3, 1);
+                    } else {
+                        log::info!("Click ignored in wireless mode");
+                    }
+                }
+                gesture::GestureAction::OffTimer => {
+                    log::info!("Switch off in {} s", config.off_timer_s);
+                    off_timer =
+                        Some(Instant::now() + Duration::from_secs(config.off_timer_s as u64));
+                }
+            }
+        }
+        if off_timer.is_some_and(|at| Instant::now() >= at) {
+            off_timer = None;
+            switches[0].turn_off(&mut miio);
         }
-        if *illumination_touched_clone.lock().unwrap() {
-            match miio.get_from_cache(2, 1) {
-                Some(Value::Boolean(value)) => {
-                    miio.set_property(2, 1, Value::Boolean(!value));
-                },
-                _ => {}
+        if let Some((server, commands)) = gatt.as_mut() {
+            while let Ok(command) = commands.try_recv() {
+                log::info!("BLE command: {:?}", command);
+                match command {
+                    ble::GattCommand::Switch(on) => {
+                        miio.set_property(switches[0].siid, 1, Value::Boolean(on))
+                    }
+                    ble::GattCommand::Mode(mode) => {
+                        miio.set_property(switches[0].siid, 2, Value::Integer(mode))
+                    }
+                };
+            }
+            let mode = match miio.get_from_cache(switches[0].siid, 2) {
+                Some(&Value::Integer(mode)) => Some(mode),
+                _ => None,
+            };
+            server.update(switches[0].state(), lux_, mode);
+        }
+
+        let (present, events) = {
+            let mut presence = presence_clone.lock().unwrap();
+            (presence.is_present(), presence.take_events())
+        };
+        miio.set_property(7, 3, Value::Boolean(present));
+        for event in events.iter() {
+            match event {
+                ble::PresenceEvent::Arrived => miio.event_occurred(7, 1),
+                ble::PresenceEvent::Departed => miio.event_occurred(7, 2),
+            };
+        }
+        let command = auto_switch_clone
+            .lock()
+            .unwrap()
+            .update(switches[0].state(), lux_, &events);
+        if let Some(on) = command {
+            if !matches!(switches[0].switch_locally(&mut miio, on), Ok(true)) {
+                auto_switch_clone.lock().unwrap().cancel_command();
             }
-            *illumination_touched_clone.lock().unwrap() = false;
         }
-        miio.set_property(7, 3, Value::Boolean(*bluetooth_matched_clone.lock().unwrap()));
         std::thread::sleep(Duration::from_millis(200));
     }
 }
-
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
 use esp_idf_hal::gpio::{InputPin, OutputPin};
 use esp_idf_hal::peripheral::Peripheral;
 use esp_idf_hal::uart::Uart;
+use std::collections::HashMap;
 
 use crate::parser::Value;
 use crate::serial::{Property, Serial};
@@ -15,6 +15,7 @@ pub struct Storage {
 pub struct IoTFramework {
     properties: HashMap<(u32, u32), Storage>,
     callbacks: HashMap<(u32, u32), Box<dyn FnMut(&Value)>>,
+    validators: HashMap<(u32, u32), Box<dyn Fn(&Value) -> bool>>,
     serial: Serial,
     model: &'static str,
     version: &'static str,
@@ -39,6 +40,7 @@ impl IoTFramework {
         Ok(IoTFramework {
             properties: HashMap::new(),
             callbacks: HashMap::new(),
+            validators: HashMap::new(),
             serial,
             model,
             version,
@@ -56,13 +58,22 @@ impl IoTFramework {
         Ok(())
     }
 
-    pub fn callback(&mut self, siid: u32, piid: u32, callback: impl FnMut(&Value) + 'static) -> &mut Self {
+    pub fn callback(
+        &mut self,
+        siid: u32,
+        piid: u32,
+        callback: impl FnMut(&Value) + 'static,
+    ) -> &mut Self {
         self.callbacks.insert((siid, piid), Box::new(callback));
         self
     }
 
     pub fn register<T: Into<Value>>(&mut self, siid: u32, piid: u32, value: T) -> &mut Self {
-        let prop = Storage { siid, piid, value: value.into() };
+        let prop = Storage {
+            siid,
+            piid,
+            value: value.into(),
+        };
         self.siid = siid;
         self.piid = piid;
         self.properties.insert((siid, piid), prop);
@@ -73,8 +84,28 @@ impl IoTFramework {
         self.properties.get(&(siid, piid)).map(|p| &p.value)
     }
 
+    /// 从 nvs 恢复属性的缓存值，不触发回调也不上报
+    pub fn restore_cached(&mut self, siid: u32, piid: u32) -> anyhow::Result<Option<&Value>> {
+        if let Some(data) = crate::nvs::load_from::<Value>(&format!("{}.{}", siid, piid))? {
+            if let Some(prop) = self.properties.get_mut(&(siid, piid)) {
+                prop.value = data;
+            }
+        }
+        Ok(self.get_from_cache(siid, piid))
+    }
+
     pub fn load(&mut self) -> anyhow::Result<&mut Self> {
-        if let Some(data) = crate::nvs::load_from::<Value>(&format!("{}.{}", self.siid, self.piid))? {
+        if let Some(data) = crate::nvs::load_from::<Value>(&format!("{}.{}", self.siid, self.piid))?
+        {
+            if !self.is_valid(&(self.siid, self.piid), &data) {
+                log::warn!(
+                    "Ignore invalid cached value of {}.{}: {}",
+                    self.siid,
+                    self.piid,
+                    data
+                );
+                return Ok(self);
+            }
             self.set_property(self.siid, self.piid, data)?;
         }
         Ok(self)
@@ -91,6 +122,18 @@ impl IoTFramework {
         self.callback(self.siid, self.piid, callback)
     }
 
+    /// 为上一个注册的属性设置校验函数，不通过的值不会被保存，也不会触发回调
+    pub fn validate(&mut self, validator: impl Fn(&Value) -> bool + 'static) -> &mut Self {
+        self.validators
+            .insert((self.siid, self.piid), Box::new(validator));
+        self
+    }
+
+    fn is_valid(&self, key: &(u32, u32), value: &Value) -> bool {
+        self.validators
+            .get(key)
+            .map_or(true, |validator| validator(value))
+    }
 
     pub fn on_get_properties(&self, props: Vec<Property>) -> String {
         let mut response = Vec::new();
@@ -98,7 +141,7 @@ impl IoTFramework {
         for prop in props {
             let key = (prop.siid, prop.piid);
             if let Some(p) = self.properties.get(&key) {
-                let code = 0;  // 操作成功
+                let code = 0; // 操作成功
                 response.push(format!("{} {} {} {}", p.siid, p.piid, code, &p.value));
             } else {
                 response.push(format!("{} {} -4003", prop.siid, prop.piid)); // 属性不存在
@@ -116,25 +159,35 @@ impl IoTFramework {
             let key = (prop.siid, prop.piid);
             match prop.value {
                 Some(value) => {
+                    if self.properties.contains_key(&key) && !self.is_valid(&key, &value) {
+                        response.push(format!("{} {} -4005", prop.siid, prop.piid)); // 属性值错误
+                        continue;
+                    }
                     if let Some(p_existing) = self.properties.get_mut(&key) {
                         p_existing.value = value.clone();
                         response.push(format!("{} {} 0", p_existing.siid, p_existing.piid));
-                        result.push(format!("{} {} {}", p_existing.siid, p_existing.piid, p_existing.value));
-                        crate::nvs::save_to::<Value>(value, &format!("{}.{}", prop.siid, prop.piid))?;
+                        result.push(format!(
+                            "{} {} {}",
+                            p_existing.siid, p_existing.piid, p_existing.value
+                        ));
+                        crate::nvs::save_to::<Value>(
+                            value,
+                            &format!("{}.{}", prop.siid, prop.piid),
+                        )?;
                         if let Some(callback) = self.callbacks.get_mut(&key) {
                             callback(&p_existing.value);
                         }
                     } else {
                         response.push(format!("{} {} -4003", prop.siid, prop.piid));
                     }
-                },
+                }
                 None => {}
             }
         }
 
         Ok(vec![
             format!("result {}", response.join(" ")),
-            format!("properties_changed {}", result.join(" "))
+            format!("properties_changed {}", result.join(" ")),
         ])
     }
 
@@ -154,6 +207,13 @@ impl IoTFramework {
         Ok(())
     }
 
+    /// 上报事件，例如 3.1 单击
+    pub fn event_occurred(&mut self, siid: u32, eiid: u32) -> anyhow::Result<()> {
+        self.serial
+            .send(&format!("event_occured {} {}", siid, eiid))?;
+        Ok(())
+    }
+
     pub fn tick(&mut self) -> anyhow::Result<()> {
         if let Ok(Some(event)) = self.serial.get_down() {
             match event {
@@ -0,0 +1,853 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use esp_idf_svc::http::{
+    server::{EspHttpConnection, EspHttpServer, Request},
+    Method,
+};
+use serde_json::json;
+
+use crate::actuator::{Easing, Position, ServoActuator, ServoMode};
+use crate::autoswitch::AutoSwitch;
+use crate::ble::{
+    DeviceIdentifier, DeviceTable, GattConfig, IrkConfig, PresenceTracker, ScanConfig, ScanStatus,
+};
+use crate::daylight::{DaylightConfig, DaylightController};
+use crate::energy::EnergyMeter;
+use crate::fault::FaultMonitor;
+use crate::gesture::{GestureAction, GestureConfig};
+use crate::indicator::IndicatorConfig;
+use crate::lux::LuxConverter;
+use crate::net::http::{new_server, parse_form, read_body_to_string, serve_file, write_result};
+use crate::net::{ApiToken, NetConfig, ProvisioningMethod};
+use crate::settings::Setting;
+use crate::switch::{GangConfig, MAX_CHANNELS};
+use crate::verifier::VerifyConfig;
+use smart_light_core::{sampling::SamplingConfig, touch::TouchConfig};
+
+// 切换到按压模式且未指定 hold_ms 时使用的按下时间
+const DEFAULT_HOLD_MS: u32 = 300;
+
+/// 联网后在局域网内提供的本地接口
+#[derive(Clone)]
+pub struct Api {
+    /// 每一路开关的舵机，下标即 channel 参数
+    pub servos: Vec<Arc<Mutex<ServoActuator>>>,
+    /// 每一路开关的故障状态
+    pub faults: Vec<Arc<Mutex<FaultMonitor>>>,
+    /// 每一路开关的光照校验设置
+    pub verify: Vec<Arc<Mutex<VerifyConfig>>>,
+    pub lux: Arc<Mutex<LuxConverter>>,
+    pub energy: Arc<Mutex<EnergyMeter>>,
+    /// 光线传感器最近一次的 ADC 读数
+    pub illumination: Arc<Mutex<Option<u16>>>,
+    pub gestures: Arc<Mutex<GestureConfig>>,
+    pub daylight: Arc<Mutex<DaylightController>>,
+    pub sampling: Arc<Mutex<SamplingConfig>>,
+    pub touch: Arc<Mutex<TouchConfig>>,
+    pub presence: Arc<Mutex<PresenceTracker>>,
+    pub auto_switch: Arc<Mutex<AutoSwitch>>,
+    /// 最近扫描到的蓝牙设备
+    pub ble_devices: Arc<Mutex<DeviceTable>>,
+    pub identifier: Arc<Mutex<DeviceIdentifier>>,
+    pub scan_config: Arc<Mutex<ScanConfig>>,
+    pub scan_status: Arc<Mutex<ScanStatus>>,
+    pub token: ApiToken,
+}
+
+/// channel 参数，缺省为第一路
+fn channel(form: &HashMap<String, String>) -> anyhow::Result<usize> {
+    match form.get("channel") {
+        Some(channel) => Ok(channel.parse()?),
+        None => Ok(0),
+    }
+}
+
+impl Api {
+    /// 按 channel 参数选择舵机，缺省为第一路
+    fn servo(&self, form: &HashMap<String, String>) -> anyhow::Result<&Mutex<ServoActuator>> {
+        let channel = channel(form)?;
+        self.servos
+            .get(channel)
+            .map(|servo| servo.as_ref())
+            .ok_or(anyhow::anyhow!("Invalid channel: {}", channel))
+    }
+
+    /// 按 channel 参数选择故障状态，缺省为第一路
+    fn fault_monitor(
+        &self,
+        form: &HashMap<String, String>,
+    ) -> anyhow::Result<&Mutex<FaultMonitor>> {
+        let channel = channel(form)?;
+        self.faults
+            .get(channel)
+            .map(|faults| faults.as_ref())
+            .ok_or(anyhow::anyhow!("Invalid channel: {}", channel))
+    }
+}
+
+/// 读取 POST 请求的表单并校验访问令牌
+fn read_form(
+    api: &Api,
+    req: &mut Request<&mut EspHttpConnection>,
+) -> anyhow::Result<HashMap<String, String>> {
+    let form = parse_form(&read_body_to_string(req)?)?;
+    api.token.check(&form)?;
+    Ok(form)
+}
+
+fn parse_position(name: Option<&String>) -> anyhow::Result<Position> {
+    match name.map(|x| x.as_str()) {
+        Some("on") => Ok(Position::On),
+        Some("off") => Ok(Position::Off),
+        Some("neutral") => Ok(Position::Neutral),
+        other => anyhow::bail!("Invalid position: {:?}", other),
+    }
+}
+
+fn servo_status(servo: &ServoActuator) -> serde_json::Value {
+    json!({
+        "calibration": servo.calibration(),
+        "duty": servo.previewing(),
+        "mode": servo.mode(),
+        "profile": servo.profile(),
+    })
+}
+
+fn status(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut status = servo_status(&api.servo(form)?.lock().unwrap());
+    status["channels"] = api.servos.len().into();
+    Ok(status)
+}
+
+fn jog(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let servo = api.servo(form)?;
+    let delta: f32 = form
+        .get("delta")
+        .ok_or(anyhow::anyhow!("Missing delta"))?
+        .parse()?;
+    let mut servo = servo.lock().unwrap();
+    servo.jog(delta)?;
+    Ok(servo_status(&servo))
+}
+
+fn preview(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut servo = api.servo(form)?.lock().unwrap();
+    let duty = match form.get("duty") {
+        Some(duty) => duty.parse()?,
+        None => servo
+            .calibration()
+            .get(parse_position(form.get("position"))?),
+    };
+    servo.preview(duty)?;
+    Ok(servo_status(&servo))
+}
+
+fn save(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let position = parse_position(form.get("position"))?;
+    let mut servo = api.servo(form)?.lock().unwrap();
+    servo.save_preview(position)?;
+    Ok(servo_status(&servo))
+}
+
+fn set_mode(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut servo = api.servo(form)?.lock().unwrap();
+    let mode = match form.get("mode").map(|x| x.as_str()) {
+        Some("hold") => ServoMode::Hold,
+        Some("press") => ServoMode::Press {
+            hold_ms: match form.get("hold_ms") {
+                Some(ms) => ms.parse()?,
+                None => match servo.mode() {
+                    ServoMode::Press { hold_ms } => hold_ms,
+                    ServoMode::Hold => DEFAULT_HOLD_MS,
+                },
+            },
+        },
+        other => anyhow::bail!("Invalid mode: {:?}", other),
+    };
+    servo.set_mode(mode)?;
+    Ok(servo_status(&servo))
+}
+
+fn set_profile(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut servo = api.servo(form)?.lock().unwrap();
+    let mut profile = servo.profile();
+    if let Some(easing) = form.get("easing") {
+        profile.easing = match easing.as_str() {
+            "step" => Easing::Step,
+            "linear" => Easing::Linear,
+            "ease_in_out" => Easing::EaseInOut,
+            other => anyhow::bail!("Invalid easing: {}", other),
+        };
+    }
+    if let Some(ms) = form.get("duration_ms") {
+        profile.duration_ms = ms.parse()?;
+    }
+    if let Some(speed) = form.get("max_speed") {
+        profile.max_speed = speed.parse()?;
+    }
+    servo.set_profile(profile)?;
+    Ok(servo_status(&servo))
+}
+
+fn gangs(api: &Api) -> serde_json::Value {
+    let config = crate::nvs::load::<GangConfig>()
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    json!({
+        "configured": config.channels,
+        "active": api.servos.len(),
+        "max": MAX_CHANNELS,
+    })
+}
+
+fn set_gangs(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let channels: u8 = form
+        .get("channels")
+        .ok_or(anyhow::anyhow!("Missing channels"))?
+        .parse()?;
+    if !(1..=MAX_CHANNELS).contains(&channels) {
+        anyhow::bail!("Channels must be between 1 and {}", MAX_CHANNELS);
+    }
+    crate::nvs::save(GangConfig { channels })?;
+    log::info!("Switch channels set to {}, restart to apply", channels);
+    Ok(gangs(api))
+}
+
+fn lux_status(api: &Api) -> serde_json::Value {
+    let raw = *api.illumination.lock().unwrap();
+    let lux = api.lux.lock().unwrap();
+    json!({
+        "raw": raw,
+        "lux": raw.map(|raw| lux.lux(raw)),
+        "curve": lux.curve(),
+    })
+}
+
+fn calibrate_lux(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let reference: f32 = form
+        .get("lux")
+        .ok_or(anyhow::anyhow!("Missing lux"))?
+        .parse()?;
+    let raw = api
+        .illumination
+        .lock()
+        .unwrap()
+        .ok_or(anyhow::anyhow!("No reading from the light sensor"))?;
+    api.lux.lock().unwrap().add_reference(raw, reference)?;
+    Ok(lux_status(api))
+}
+
+fn reset_lux(api: &Api) -> anyhow::Result<serde_json::Value> {
+    api.lux.lock().unwrap().reset()?;
+    Ok(lux_status(api))
+}
+
+fn parse_action(name: &str) -> anyhow::Result<GestureAction> {
+    match name {
+        "none" => Ok(GestureAction::None),
+        "toggle" => Ok(GestureAction::Toggle),
+        "click" => Ok(GestureAction::Click),
+        "off_timer" => Ok(GestureAction::OffTimer),
+        other => anyhow::bail!("Invalid action: {}", other),
+    }
+}
+
+fn gestures(api: &Api) -> serde_json::Value {
+    json!(*api.gestures.lock().unwrap())
+}
+
+/// 只修改请求中给出的字段
+fn set_gestures(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = *api.gestures.lock().unwrap();
+    if let Some(action) = form.get("single_tap") {
+        config.single_tap = parse_action(action)?;
+    }
+    if let Some(action) = form.get("double_tap") {
+        config.double_tap = parse_action(action)?;
+    }
+    if let Some(action) = form.get("long_cover") {
+        config.long_cover = parse_action(action)?;
+    }
+    if let Some(ms) = form.get("double_tap_ms") {
+        config.double_tap_ms = ms.parse()?;
+    }
+    if let Some(ms) = form.get("long_cover_ms") {
+        config.long_cover_ms = ms.parse()?;
+    }
+    if let Some(s) = form.get("off_timer_s") {
+        config.off_timer_s = s.parse()?;
+    }
+    config.save()?;
+    *api.gestures.lock().unwrap() = config;
+    Ok(gestures(api))
+}
+
+fn touch(api: &Api) -> serde_json::Value {
+    json!(*api.touch.lock().unwrap())
+}
+
+fn set_touch(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = *api.touch.lock().unwrap();
+    if let Some(value) = form.get("sensitivity") {
+        config.sensitivity = value.parse()?;
+    }
+    if let Some(value) = form.get("noise") {
+        config.noise = value.parse()?;
+    }
+    if let Some(ms) = form.get("baseline_ms") {
+        config.baseline_ms = ms.parse()?;
+    }
+    if let Some(ms) = form.get("cooldown_ms") {
+        config.cooldown_ms = ms.parse()?;
+    }
+    config.save()?;
+    *api.touch.lock().unwrap() = config;
+    Ok(touch(api))
+}
+
+fn daylight_status(api: &Api) -> serde_json::Value {
+    let daylight = api.daylight.lock().unwrap();
+    json!({
+        "config": daylight.config(),
+        "lamp_offset": daylight.lamp_offset(),
+    })
+}
+
+fn set_daylight(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config: DaylightConfig = api.daylight.lock().unwrap().config();
+    if let Some(lux) = form.get("on_below") {
+        config.on_below = lux.parse()?;
+    }
+    if let Some(lux) = form.get("off_above") {
+        config.off_above = lux.parse()?;
+    }
+    if let Some(s) = form.get("dwell_s") {
+        config.dwell_s = s.parse()?;
+    }
+    if let Some(s) = form.get("min_hold_s") {
+        config.min_hold_s = s.parse()?;
+    }
+    api.daylight.lock().unwrap().set_config(config)?;
+    Ok(daylight_status(api))
+}
+
+fn faults(api: &Api) -> serde_json::Value {
+    let faults: Vec<String> = api
+        .faults
+        .iter()
+        .map(|faults| format!("{:?}", faults.lock().unwrap().fault()))
+        .collect();
+    json!({"faults": faults})
+}
+
+fn clear_fault(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    api.fault_monitor(form)?.lock().unwrap().reset()?;
+    Ok(faults(api))
+}
+
+fn verify(api: &Api) -> serde_json::Value {
+    let configs: Vec<VerifyConfig> = api.verify.iter().map(|x| *x.lock().unwrap()).collect();
+    json!({"channels": configs})
+}
+
+fn set_verify(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let channel = channel(form)?;
+    let shared = api
+        .verify
+        .get(channel)
+        .ok_or(anyhow::anyhow!("Invalid channel: {}", channel))?;
+    let mut config = *shared.lock().unwrap();
+    if let Some(enabled) = form.get("enabled") {
+        config.enabled = enabled.parse()?;
+    }
+    if let Some(retries) = form.get("retries") {
+        config.retries = retries.parse()?;
+    }
+    if let Some(ms) = form.get("delay_ms") {
+        config.delay_ms = ms.parse()?;
+    }
+    if let Some(threshold) = form.get("threshold") {
+        config.threshold = threshold.parse()?;
+    }
+    config.save(channel)?;
+    *shared.lock().unwrap() = config;
+    Ok(verify(api))
+}
+
+fn energy(api: &Api) -> serde_json::Value {
+    json!(api.energy.lock().unwrap().config())
+}
+
+fn set_energy(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut energy = api.energy.lock().unwrap();
+    let mut config = energy.config();
+    if let Some(wattage) = form.get("wattage") {
+        config.wattage = wattage.parse()?;
+    }
+    if let Some(period) = form.get("period") {
+        config.period = period.parse()?;
+    }
+    energy.set_config(config)?;
+    Ok(json!(energy.config()))
+}
+
+fn set_indicator(form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = IndicatorConfig::load()?;
+    if let Some(pin) = form.get("pin") {
+        config.pin = pin.parse()?;
+    }
+    config.save()?;
+    Ok(json!(config))
+}
+
+fn sampling(api: &Api) -> serde_json::Value {
+    json!(*api.sampling.lock().unwrap())
+}
+
+fn set_sampling(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = *api.sampling.lock().unwrap();
+    if let Some(ms) = form.get("interval_ms") {
+        config.interval_ms = ms.parse()?;
+    }
+    if let Some(n) = form.get("oversample") {
+        config.oversample = n.parse()?;
+    }
+    if let Some(n) = form.get("median") {
+        config.median = n.parse()?;
+    }
+    if let Some(n) = form.get("average") {
+        config.average = n.parse()?;
+    }
+    if let Some(value) = form.get("outlier") {
+        config.outlier = value.parse()?;
+    }
+    if let Some(n) = form.get("outlier_limit") {
+        config.outlier_limit = n.parse()?;
+    }
+    config.save()?;
+    *api.sampling.lock().unwrap() = config;
+    Ok(sampling(api))
+}
+
+fn presence_status(api: &Api) -> serde_json::Value {
+    let presence = api.presence.lock().unwrap();
+    let devices: Vec<_> = presence
+        .last_seen()
+        .into_iter()
+        .map(|(rule, elapsed, present)| {
+            json!({
+                "rule": rule,
+                "last_seen_s": elapsed.as_secs(),
+                "present": present,
+            })
+        })
+        .collect();
+    json!({
+        "config": presence.config(),
+        "present": presence.is_present(),
+        "devices": devices,
+    })
+}
+
+fn set_presence(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = api.presence.lock().unwrap().config();
+    if let Some(n) = form.get("away_scans") {
+        config.away_scans = n.parse()?;
+    }
+    if let Some(s) = form.get("away_s") {
+        config.away_s = s.parse()?;
+    }
+    api.presence.lock().unwrap().set_config(config)?;
+    Ok(presence_status(api))
+}
+
+fn auto_switch(api: &Api) -> serde_json::Value {
+    json!(api.auto_switch.lock().unwrap().config())
+}
+
+fn set_auto_switch(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = api.auto_switch.lock().unwrap().config();
+    if let Some(lux) = form.get("dark_below") {
+        config.dark_below = lux.parse()?;
+    }
+    if let Some(s) = form.get("off_delay_s") {
+        config.off_delay_s = s.parse()?;
+    }
+    if let Some(s) = form.get("override_s") {
+        config.override_s = s.parse()?;
+    }
+    api.auto_switch.lock().unwrap().set_config(config)?;
+    Ok(auto_switch(api))
+}
+
+fn gatt() -> anyhow::Result<serde_json::Value> {
+    // 不返回 PIN
+    Ok(json!({"enabled": GattConfig::load()?.enabled}))
+}
+
+fn set_gatt(form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = GattConfig::load()?;
+    if let Some(enabled) = form.get("enabled") {
+        config.enabled = enabled.parse()?;
+    }
+    if let Some(pin) = form.get("pin") {
+        // 令牌之外还需要当前 PIN，避免拿到令牌的人直接接管蓝牙控制
+        let current: u32 = form
+            .get("current_pin")
+            .ok_or(anyhow::anyhow!("Missing current_pin"))?
+            .parse()?;
+        if current != config.pin {
+            anyhow::bail!("Invalid current_pin");
+        }
+        config.pin = pin.parse()?;
+    }
+    config.save()?;
+    gatt()
+}
+
+fn irks(api: &Api) -> anyhow::Result<serde_json::Value> {
+    // IRK 属于密钥，只返回数量
+    Ok(json!({"count": api.identifier.lock().unwrap().irk_count()}))
+}
+
+fn set_irks(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let list = form
+        .get("irks")
+        .map(|x| {
+            x.split(',')
+                .map(|x| x.trim().to_string())
+                .filter(|x| !x.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    api.identifier.lock().unwrap().set_irks(IrkConfig(list))?;
+    irks(api)
+}
+
+fn scanner(api: &Api) -> serde_json::Value {
+    let config = *api.scan_config.lock().unwrap();
+    json!({
+        "config": config,
+        "duty_cycle": config.duty_cycle(),
+        "status": *api.scan_status.lock().unwrap(),
+    })
+}
+
+fn set_scanner(api: &Api, form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let mut config = *api.scan_config.lock().unwrap();
+    if let Some(enabled) = form.get("enabled") {
+        config.enabled = enabled.parse()?;
+    }
+    if let Some(ms) = form.get("scan_ms") {
+        config.scan_ms = ms.parse()?;
+    }
+    if let Some(ms) = form.get("idle_ms") {
+        config.idle_ms = ms.parse()?;
+    }
+    if let Some(ms) = form.get("interval_ms") {
+        config.interval_ms = ms.parse()?;
+    }
+    if let Some(ms) = form.get("window_ms") {
+        config.window_ms = ms.parse()?;
+    }
+    config.save()?;
+    *api.scan_config.lock().unwrap() = config;
+    Ok(scanner(api))
+}
+
+fn set_provisioning(form: &HashMap<String, String>) -> anyhow::Result<serde_json::Value> {
+    let method = match form.get("method").map(|x| x.as_str()) {
+        Some("softap") => Some(ProvisioningMethod::SoftAp),
+        Some("ble") => Some(ProvisioningMethod::Ble),
+        None => None,
+        other => anyhow::bail!("Invalid method: {:?}", other),
+    };
+    let reset = form
+        .get("reset")
+        .map(|x| x.parse())
+        .transpose()?
+        .unwrap_or(false);
+    if let Some(method) = method {
+        method.save()?;
+    }
+    if reset {
+        // 配网方式只在没有网络配置时使用，清除后重启或断线时重新配网
+        crate::nvs::remove::<NetConfig>()?;
+        log::info!("NetConfig removed, restart to provision again");
+    }
+    Ok(json!({"method": ProvisioningMethod::load()?}))
+}
+
+pub fn serve(api: Api) -> anyhow::Result<EspHttpServer<'static>> {
+    let mut http = new_server()?;
+
+    // 以下接口均可附带 channel=<开关序号>，缺省为第一路
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/servo", Method::Get, move |req| {
+        let query = req
+            .uri()
+            .split_once('?')
+            .map(|(_, query)| query.to_string())
+            .unwrap_or_default();
+        let result = parse_form(&query).and_then(|form| status(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    // 微调: delta=<占空比增量>
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/servo/jog", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| jog(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    // 预览: position=on|off|neutral 或 duty=<占空比>
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/servo/preview", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| preview(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    // 工作模式: mode=hold|press, hold_ms=<按压模式下保持按下的时间>
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/servo/mode", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_mode(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    // 运动曲线: easing=step|linear|ease_in_out, duration_ms=<动作时间>, max_speed=<占空比每秒，0 不限制>
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/servo/profile", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_profile(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    // 保存: position=on|off|neutral
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/servo/save", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| save(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/gangs", Method::Get, move |req| {
+        write_result(req, Ok(gangs(&api_)))
+    })?;
+
+    // 开关路数: channels=<1..3>，重启后生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/gangs", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_gangs(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/lux", Method::Get, move |req| {
+        write_result(req, Ok(lux_status(&api_)))
+    })?;
+
+    // 光照校准: lux=<照度计在传感器处测得的照度>，记录当前读数作为一个参考点
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/lux/calibrate", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| calibrate_lux(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    // 清除参考点，恢复默认的分压模型
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/lux/reset", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|_| reset_lux(&api_));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/gestures", Method::Get, move |req| {
+        write_result(req, Ok(gestures(&api_)))
+    })?;
+
+    // 手势设置: single_tap|double_tap|long_cover=none|toggle|click|off_timer,
+    // double_tap_ms, long_cover_ms, off_timer_s，立即生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/gestures", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_gestures(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/touch", Method::Get, move |req| {
+        write_result(req, Ok(touch(&api_)))
+    })?;
+
+    // 触摸检测: sensitivity, noise, baseline_ms, cooldown_ms，立即生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/touch", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_touch(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/daylight", Method::Get, move |req| {
+        write_result(req, Ok(daylight_status(&api_)))
+    })?;
+
+    // 光照自动化设置: on_below, off_above (lux), dwell_s, min_hold_s，开关由属性 8.2 控制
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/daylight", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_daylight(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/verify", Method::Get, move |req| {
+        write_result(req, Ok(verify(&api_)))
+    })?;
+
+    // 开关确认: channel=<路数>, enabled=<是否校验>, retries=<重新按压次数>, delay_ms=<等待时间>, threshold=<最小变化量>
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/verify", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_verify(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/fault", Method::Get, move |req| {
+        write_result(req, Ok(faults(&api_)))
+    })?;
+
+    // 解除过载或灯具无响应故障: channel=<路数>，过温故障不能手动解除
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/fault/clear", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| clear_fault(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/energy", Method::Get, move |req| {
+        write_result(req, Ok(energy(&api_)))
+    })?;
+
+    // 耗电量统计: wattage=<每路灯具功率 W>, period=<非累加形式的清零周期 s>
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/energy", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_energy(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    http.fn_handler::<anyhow::Error, _>("/api/indicator", Method::Get, |req| {
+        write_result(req, IndicatorConfig::load().map(|config| json!(config)))
+    })?;
+
+    // 指示灯: pin=<GPIO 编号>，重启后生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/indicator", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_indicator(&form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/sampling", Method::Get, move |req| {
+        write_result(req, Ok(sampling(&api_)))
+    })?;
+
+    // 光线传感器采样设置: interval_ms, oversample, median, average, outlier, outlier_limit，立即生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/sampling", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_sampling(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/presence", Method::Get, move |req| {
+        write_result(req, Ok(presence_status(&api_)))
+    })?;
+
+    // 在场检测设置: away_scans, away_s，满足任一条件即认为离开，0 表示不使用
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/presence", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_presence(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/autoswitch", Method::Get, move |req| {
+        write_result(req, Ok(auto_switch(&api_)))
+    })?;
+
+    // 在场自动开关设置: dark_below (lux), off_delay_s, override_s，开关由属性 7.5 控制
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/autoswitch", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_auto_switch(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    http.fn_handler::<anyhow::Error, _>("/api/ble", Method::Get, |req| write_result(req, gatt()))?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/ble/devices", Method::Get, move |req| {
+        let devices = api_.ble_devices.lock().unwrap().to_json();
+        write_result(req, Ok(devices))
+    })?;
+
+    // 蓝牙本地控制: enabled=true|false, pin=<6 位数字>，重启后生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/ble", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_gatt(&form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/ble/scanner", Method::Get, move |req| {
+        write_result(req, Ok(scanner(&api_)))
+    })?;
+
+    // 蓝牙扫描: enabled=true|false, scan_ms, idle_ms, interval_ms, window_ms，下一轮扫描生效
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/ble/scanner", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_scanner(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/ble/irks", Method::Get, move |req| {
+        write_result(req, irks(&api_))
+    })?;
+
+    // 用于解析手机私有地址的 IRK: irks=<32 位十六进制>,...，为空时清除
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/ble/irks", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_irks(&api_, &form));
+        write_result(req, result)
+    })?;
+
+    http.fn_handler::<anyhow::Error, _>("/api/provisioning", Method::Get, |req| {
+        let result = ProvisioningMethod::load().map(|method| json!({"method": method}));
+        write_result(req, result)
+    })?;
+
+    // 配网方式: method=softap|ble，下次需要配网时生效；reset=true 清除保存的网络配置
+    let api_ = api.clone();
+    http.fn_handler::<anyhow::Error, _>("/api/provisioning", Method::Post, move |mut req| {
+        let result = read_form(&api_, &mut req).and_then(|form| set_provisioning(&form));
+        write_result(req, result)
+    })?;
+
+    http.fn_handler::<anyhow::Error, _>("/", Method::Get, |req| {
+        req.into_response(302, None, &[("Location", "/calibration")])?;
+        Ok(())
+    })?;
+
+    http.fn_handler::<anyhow::Error, _>("/calibration", Method::Get, |req| {
+        serve_file(req, "index.html")
+    })?;
+
+    http.fn_handler::<anyhow::Error, _>("*", Method::Get, |req| {
+        let path = req.uri().trim_start_matches('/').to_string();
+        serve_file(req, &path)
+    })?;
+
+    log::info!("Local API server started");
+    Ok(http)
+}
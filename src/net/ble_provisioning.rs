@@ -0,0 +1,140 @@
+use std::sync::{
+    mpsc::{self, Receiver},
+    Arc,
+};
+
+use esp32_nimble::{
+    utilities::mutex::Mutex, uuid128, BLEAdvertisementData, BLECharacteristic, BLEDevice,
+    NimbleProperties,
+};
+use esp_idf_svc::{eventloop::EspSystemEventLoop, wifi::EspWifi};
+use serde_json::json;
+
+use super::{bupt, NetConfig, Wifi};
+
+/// 手机写入的配网信息
+///
+/// ```json
+/// {"type": "bupt", "username": "...", "password": "..."}
+/// {"type": "wifi", "ssid": "...", "password": "..."}
+/// ```
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Request {
+    Bupt { username: String, password: String },
+    Wifi { ssid: String, password: String },
+}
+
+impl From<Request> for NetConfig {
+    fn from(request: Request) -> Self {
+        match request {
+            Request::Bupt { username, password } => {
+                NetConfig::BuptPortal(bupt::BuptAccount { username, password })
+            }
+            Request::Wifi { ssid, password } => NetConfig::NormalWifi(Wifi { ssid, password }),
+        }
+    }
+}
+
+/// 通过蓝牙配网，手机连接后向配置特征写入 JSON，并订阅状态特征获取进度
+///
+/// 状态为 JSON：`{"state": "waiting|connecting|connected|failed", "message": ...}`
+pub struct BleProvisioner {
+    status: Arc<Mutex<BLECharacteristic>>,
+    requests: Receiver<anyhow::Result<NetConfig>>,
+}
+
+impl BleProvisioner {
+    pub fn new() -> anyhow::Result<Self> {
+        let ble_device = BLEDevice::take();
+        crate::ble::GattConfig::load()?.apply_security();
+
+        let server = ble_device.get_server();
+        let service_uuid = uuid128!("6e4a0101-6d1f-4c4a-9b1e-7c3d2a5b8f10");
+        let service = server.create_service(service_uuid);
+        let config = service.lock().create_characteristic(
+            uuid128!("6e4a0102-6d1f-4c4a-9b1e-7c3d2a5b8f10"),
+            NimbleProperties::WRITE | NimbleProperties::WRITE_ENC | NimbleProperties::WRITE_AUTHEN,
+        );
+        let status = service.lock().create_characteristic(
+            uuid128!("6e4a0103-6d1f-4c4a-9b1e-7c3d2a5b8f10"),
+            NimbleProperties::READ
+                | NimbleProperties::READ_ENC
+                | NimbleProperties::READ_AUTHEN
+                | NimbleProperties::NOTIFY,
+        );
+
+        let (tx, requests) = mpsc::channel();
+        config.lock().on_write(move |args| {
+            let request = std::str::from_utf8(args.recv_data())
+                .map_err(anyhow::Error::from)
+                .and_then(|x| Ok(serde_json::from_str::<Request>(x)?))
+                .map(NetConfig::from);
+            let _ = tx.send(request);
+        });
+
+        // 先暂停蓝牙控制服务的广播，再换成配网服务的广播数据
+        crate::ble::set_provisioning(true);
+        super::set_state(super::NetState::Provisioning);
+        let mut advertising = ble_device.get_advertising().lock();
+        let _ = advertising.stop();
+        advertising.set_data(
+            BLEAdvertisementData::new()
+                .name(crate::ble::DEVICE_NAME)
+                .add_service_uuid(service_uuid),
+        )?;
+        advertising.start()?;
+        drop(advertising);
+
+        let provisioner = Self { status, requests };
+        provisioner.report("waiting", None);
+        log::info!("Waiting for BLE provisioning");
+        Ok(provisioner)
+    }
+
+    fn report(&self, state: &str, message: Option<String>) {
+        let status = json!({"state": state, "message": message}).to_string();
+        self.status.lock().set_value(status.as_bytes()).notify();
+    }
+
+    /// 等待手机写入配网信息并尝试连接，失败后继续等待下一次写入
+    pub fn wait(
+        &self,
+        wifi: &mut EspWifi<'static>,
+        sys_loop: EspSystemEventLoop,
+    ) -> anyhow::Result<()> {
+        loop {
+            let config = match self.requests.recv()? {
+                Ok(config) => config,
+                Err(e) => {
+                    log::warn!("Invalid provisioning request: {:?}", e);
+                    self.report("failed", Some(e.to_string()));
+                    continue;
+                }
+            };
+            log::info!("Received NetConfig over BLE: {:?}", config);
+            self.report("connecting", None);
+            match super::connect_wifi_with_config(wifi, config.clone(), sys_loop.clone()) {
+                Ok(_) => {
+                    crate::nvs::save(config)?;
+                    let token = super::ApiToken::load()?;
+                    self.report("connected", Some(format!("token: {}", token.as_str())));
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::error!("BLE provisioning failed: {:?}", e);
+                    let _ = wifi.disconnect();
+                    let _ = wifi.stop();
+                    self.report("failed", Some(e.to_string()));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for BleProvisioner {
+    fn drop(&mut self) {
+        let _ = BLEDevice::take().get_advertising().lock().stop();
+        crate::ble::set_provisioning(false);
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use esp_idf_svc::{
+    http::server::{Configuration, EspHttpConnection, EspHttpServer, Request},
+    io::Write,
+};
+
+use include_dir::{include_dir, Dir};
+
+static FRONTEND: Dir = include_dir!("$OUT_DIR/frontend");
+
+const STACK_SIZE: usize = 10240;
+
+include!(concat!(env!("OUT_DIR"), "/mime.rs"));
+
+pub fn new_server() -> anyhow::Result<EspHttpServer<'static>> {
+    Ok(EspHttpServer::new(&Configuration {
+        stack_size: STACK_SIZE,
+        uri_match_wildcard: true,
+        ..Default::default()
+    })?)
+}
+
+pub fn read_body_to_string(req: &mut Request<&mut EspHttpConnection>) -> anyhow::Result<String> {
+    let mut body = Vec::new();
+    let mut buffer = [0; 4096];
+
+    loop {
+        let bytes_read = req.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        body.extend_from_slice(&buffer[..bytes_read]);
+    }
+
+    Ok(String::from_utf8(body)?)
+}
+
+/// 解析 application/x-www-form-urlencoded 格式的请求体
+pub fn parse_form(body: &str) -> anyhow::Result<HashMap<String, String>> {
+    let mut form = HashMap::new();
+    for pair in body.split('&').filter(|pair| !pair.is_empty()) {
+        let mut pair = pair.split('=');
+        let key = pair.next().ok_or(anyhow::anyhow!("Invalid body"))?;
+        let value = pair.next().ok_or(anyhow::anyhow!("Invalid body"))?;
+        form.insert(
+            urlencoding::decode(key)?.into_owned(),
+            urlencoding::decode(value)?.into_owned(),
+        );
+    }
+    Ok(form)
+}
+
+/// 返回前端构建产物中的文件（已 gzip 压缩）
+pub fn serve_file(req: Request<&mut EspHttpConnection>, path: &str) -> anyhow::Result<()> {
+    match FRONTEND.get_file(path) {
+        Some(file) => {
+            let ext = path.split('.').last().unwrap_or("");
+            let mime = MIME_TYPES
+                .iter()
+                .find(|(ext_, _)| ext == *ext_)
+                .map(|(_, mime)| *mime)
+                .unwrap_or("application/octet-stream");
+            req.into_response(
+                200,
+                None,
+                &[("Content-Type", mime), ("Content-Encoding", "gzip")],
+            )?
+            .write_all(file.contents())?;
+        }
+        None => {
+            req.into_response(404, None, &[])?;
+        }
+    }
+    Ok(())
+}
+
+/// 以 `{"code": 0, ...}` 或 `{"code": 1, "message": ...}` 的格式返回结果
+pub fn write_result(
+    req: Request<&mut EspHttpConnection>,
+    result: anyhow::Result<serde_json::Value>,
+) -> anyhow::Result<()> {
+    let body = match result {
+        Ok(mut data) => {
+            data["code"] = 0.into();
+            data
+        }
+        Err(e) => serde_json::json!({"code": 1, "message": e.to_string()}),
+    };
+    req.into_ok_response()?
+        .write_all(body.to_string().as_bytes())?;
+    Ok(())
+}
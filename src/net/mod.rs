@@ -1,5 +1,11 @@
+pub mod api;
+mod ble_provisioning;
 mod bupt;
+mod http;
 mod provisioning;
+mod token;
+
+pub use token::ApiToken;
 
 use anyhow::{bail, Result};
 use esp_idf_hal::{delay, modem::Modem};
@@ -8,7 +14,7 @@ use esp_idf_svc::{
     log::set_target_level,
     wifi::{AuthMethod, BlockingWifi, ClientConfiguration, Configuration, EspWifi},
 };
-use std::fmt;
+use std::{fmt, sync::Mutex};
 
 fn connect_wifi_with_config(
     esp_wifi: &mut EspWifi<'static>,
@@ -133,6 +139,55 @@ pub fn generate_random_mac() -> [u8; 6] {
     mac
 }
 
+/// 没有保存网络配置时使用的配网方式
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProvisioningMethod {
+    /// 开启热点，通过网页登录
+    SoftAp,
+    /// 通过蓝牙写入配置，适用于不愿意保持连接无法上网的热点的手机
+    Ble,
+}
+
+impl Default for ProvisioningMethod {
+    fn default() -> Self {
+        if cfg!(feature = "ble_provisioning") {
+            ProvisioningMethod::Ble
+        } else {
+            ProvisioningMethod::SoftAp
+        }
+    }
+}
+
+impl ProvisioningMethod {
+    pub fn load() -> anyhow::Result<Self> {
+        Ok(crate::nvs::load::<ProvisioningMethod>()?.unwrap_or_default())
+    }
+
+    pub fn save(self) -> anyhow::Result<()> {
+        crate::nvs::save(self)?;
+        log::info!("Provisioning method: {:?}", self);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetState {
+    Connecting,
+    Provisioning,
+    Online,
+}
+
+static STATE: Mutex<NetState> = Mutex::new(NetState::Connecting);
+
+pub fn state() -> NetState {
+    *STATE.lock().unwrap()
+}
+
+fn set_state(state: NetState) {
+    *STATE.lock().unwrap() = state;
+}
+
 pub struct NetManager {
     pub wifi: EspWifi<'static>,
     pub sysloop: EspSystemEventLoop,
@@ -145,35 +200,53 @@ impl NetManager {
         let esp_wifi = EspWifi::new(modem, sysloop.clone(), Some(nvs))?;
         Ok(Self {
             wifi: esp_wifi,
-            sysloop
+            sysloop,
         })
     }
 
+    /// 检查 Wi-Fi 是否仍然连接，断开时将状态改回连接中
+    pub fn is_connected(&mut self) -> bool {
+        let connected = self.wifi.is_connected().unwrap_or(false);
+        if !connected {
+            set_state(NetState::Connecting);
+        }
+        connected
+    }
+
+    /// 没有保存网络配置，下次连接时需要配网
+    pub fn needs_provisioning(&self) -> Result<bool> {
+        Ok(cfg!(feature = "clean_nvs") || crate::nvs::load::<NetConfig>()?.is_none())
+    }
+
     pub fn connect(&mut self) -> Result<()> {
         set_target_level("wifi", log::LevelFilter::Warn)?;
         set_target_level("wifi_init", log::LevelFilter::Warn)?;
 
+        // 断线重连前先停止之前的连接
+        let _ = self.wifi.disconnect();
+        let _ = self.wifi.stop();
+
         #[cfg(feature = "clean_nvs")]
         crate::nvs::remove::<NetConfig>()?;
 
+        set_state(NetState::Connecting);
         match crate::nvs::load::<NetConfig>()? {
             Some(config) => {
                 log::info!("Loaded NetConfig: {:?}", &config);
-                connect_wifi_with_config(
-                    &mut self.wifi,
-                    config,
-                    self.sysloop.clone(),
-                )
-            }
-            None => {
-                let p = provisioning::Provisioner::new(
-                    &mut self.wifi,
-                    self.sysloop.clone(),
-                )?;
-                p.wait();
-                Ok(())
+                connect_wifi_with_config(&mut self.wifi, config, self.sysloop.clone())?;
             }
+            None => match ProvisioningMethod::load()? {
+                ProvisioningMethod::SoftAp => {
+                    let p = provisioning::Provisioner::new(&mut self.wifi, self.sysloop.clone())?;
+                    p.wait();
+                }
+                ProvisioningMethod::Ble => {
+                    let p = ble_provisioning::BleProvisioner::new()?;
+                    p.wait(&mut self.wifi, self.sysloop.clone())?;
+                }
+            },
         }
-}
-
+        set_state(NetState::Online);
+        Ok(())
+    }
 }
@@ -26,15 +26,11 @@ use esp_idf_svc::{
     },
 };
 
-use include_dir::{include_dir, Dir};
-
 use log::*;
 
 use crate::net::bupt;
+use crate::net::http::{parse_form, read_body_to_string, serve_file};
 
-static FRONTEND: Dir = include_dir!("$OUT_DIR/frontend");
-
-const STACK_SIZE: usize = 10240;
 const SSID: &str = "smart-light";
 // Wi-Fi channel, between 1 and 11
 const CHANNEL: u8 = 11;
@@ -42,23 +38,6 @@ const CHANNEL: u8 = 11;
 const IP: Ipv4Addr = Ipv4Addr::new(192, 168, 71, 1);
 const IP_STRING: &str = "192.168.71.1";
 
-include!(concat!(env!("OUT_DIR"), "/mime.rs"));
-
-fn read_body_to_string(req: &mut Request<&mut EspHttpConnection>) -> anyhow::Result<String> {
-    let mut body = Vec::new();
-    let mut buffer = [0; 4096];
-
-    loop {
-        let bytes_read = req.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        body.extend_from_slice(&buffer[..bytes_read]);
-    }
-
-    Ok(String::from_utf8(body)?)
-}
-
 fn check_host_and_log<'a, 'b>(
     req: Request<&'a mut EspHttpConnection<'b>>,
 ) -> anyhow::Result<Option<Request<&'a mut EspHttpConnection<'b>>>> {
@@ -87,38 +66,22 @@ pub struct Provisioner {
     http: EspHttpServer<'static>,
 }
 
-
 impl Provisioner {
     pub fn new(wifi: &mut EspWifi<'static>, sys_loop: EspSystemEventLoop) -> anyhow::Result<Self> {
         setup_ap(wifi, sys_loop)?;
+        super::set_state(super::NetState::Provisioning);
 
         let mut dns = DnsServer::new(IP);
         dns.start()?;
 
-        let mut http = EspHttpServer::new(&esp_idf_svc::http::server::Configuration {
-            stack_size: STACK_SIZE,
-            uri_match_wildcard: true,
-            ..Default::default()
-        })?;
+        let mut http = super::http::new_server()?;
 
         let finished = Arc::new((Mutex::new(false), Condvar::new()));
         let finished1 = Arc::clone(&finished);
 
         http.fn_handler::<anyhow::Error, _>("/", Method::Get, |req| {
             if let Some(req) = check_host_and_log(req)? {
-                match FRONTEND.get_file("index.html") {
-                    Some(file) => {
-                        req.into_response(
-                            200,
-                            None,
-                            &[("Content-Type", "text/html"), ("Content-Encoding", "gzip")],
-                        )?
-                        .write_all(file.contents())?;
-                    }
-                    None => {
-                        req.into_response(404, None, &[])?;
-                    }
-                }
+                serve_file(req, "index.html")?;
             }
             Ok(())
         })?;
@@ -128,32 +91,25 @@ impl Provisioner {
                 let body = read_body_to_string(&mut req)?;
 
                 if req.header("Content-Type") == Some("application/x-www-form-urlencoded") {
-                    let mut username = None;
-                    let mut password = None;
-                    for pair in body.split('&') {
-                        let mut pair = pair.split('=');
-                        let key = pair.next().ok_or(anyhow::anyhow!("Invalid body"))?;
-                        let value = pair.next().ok_or(anyhow::anyhow!("Invalid body"))?;
-                        let key = urlencoding::decode(key)?;
-                        let value = urlencoding::decode(value)?;
-                        match key.as_ref() {
-                            "username" => username = Some(value),
-                            "password" => password = Some(value),
-                            _ => {}
-                        }
-                    }
+                    let mut form = parse_form(&body)?;
                     let config = bupt::BuptAccount {
-                        username: username
-                            .ok_or(anyhow::anyhow!("Missing username"))?
-                            .to_string(),
-                        password: password
-                            .ok_or(anyhow::anyhow!("Missing password"))?
-                            .to_string(),
+                        username: form
+                            .remove("username")
+                            .ok_or(anyhow::anyhow!("Missing username"))?,
+                        password: form
+                            .remove("password")
+                            .ok_or(anyhow::anyhow!("Missing password"))?,
                     };
                     match bupt::login(&config) {
                         Ok(_) => {
-                            req.into_ok_response()?
-                                .write_all(json!({"code": 0}).to_string().as_bytes())?;
+                            // 联网后访问本地接口所需的令牌和蓝牙配对 PIN 只在这里告知用户
+                            let token = super::ApiToken::load()?;
+                            let pin = crate::ble::GattConfig::load()?.pin;
+                            req.into_ok_response()?.write_all(
+                                json!({"code": 0, "token": token.as_str(), "pin": format!("{:06}", pin)})
+                                    .to_string()
+                                    .as_bytes(),
+                            )?;
                             let (_lock, cvar) = &*finished1;
                             crate::nvs::save(super::NetConfig::BuptPortal(config)).map_err(
                                 |x| {
@@ -181,25 +137,8 @@ impl Provisioner {
 
         http.fn_handler::<anyhow::Error, _>("*", Method::Get, |req| {
             if let Some(req) = check_host_and_log(req)? {
-                match FRONTEND.get_file(req.uri().trim_start_matches('/')) {
-                    Some(file) => {
-                        let ext = req.uri().split('.').last().unwrap_or("");
-                        let mime = MIME_TYPES
-                            .iter()
-                            .find(|(ext_, _)| ext == *ext_)
-                            .map(|(_, mime)| *mime)
-                            .unwrap_or("application/octet-stream");
-                        req.into_response(
-                            200,
-                            None,
-                            &[("Content-Type", mime), ("Content-Encoding", "gzip")],
-                        )?
-                        .write_all(file.contents())?;
-                    }
-                    None => {
-                        req.into_response(404, None, &[])?;
-                    }
-                }
+                let path = req.uri().trim_start_matches('/').to_string();
+                serve_file(req, &path)?;
             }
             Ok(())
         })?;
@@ -224,7 +163,6 @@ impl Provisioner {
 }
 
 fn setup_ap(esp_wifi: &mut EspWifi<'static>, sys_loop: EspSystemEventLoop) -> anyhow::Result<()> {
-
     esp_wifi.swap_netif_ap(EspNetif::new_with_conf(&NetifConfiguration {
         key: "WIFI_AP_DEF_BYR_PET".try_into().unwrap(),
         description: "ap".try_into().unwrap(),
@@ -0,0 +1,43 @@
+use std::collections::HashMap;
+
+use esp_idf_svc::sys::esp_random;
+
+/// 局域网接口的访问令牌，首次启动时随机生成并保存在 nvs 中
+///
+/// 所有修改设置或驱动舵机的 POST 请求都需要附带 `token=<令牌>`。
+/// 令牌在配网成功时返回给手机，也会在启动时打印到串口日志。
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct ApiToken(String);
+
+impl ApiToken {
+    pub fn load() -> anyhow::Result<Self> {
+        if let Some(token) = crate::nvs::load::<ApiToken>()? {
+            return Ok(token);
+        }
+        let token = ApiToken(format!("{:08x}{:08x}", unsafe { esp_random() }, unsafe {
+            esp_random()
+        }));
+        crate::nvs::save(token.clone())?;
+        log::info!("Generated a new local API token");
+        Ok(token)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn check(&self, form: &HashMap<String, String>) -> anyhow::Result<()> {
+        let token = form.get("token").map(|x| x.as_bytes()).unwrap_or_default();
+        // 逐字节比较全部内容，避免通过响应时间猜测令牌
+        let matched = token.len() == self.0.len()
+            && token
+                .iter()
+                .zip(self.0.as_bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0;
+        if !matched {
+            anyhow::bail!("Invalid token");
+        }
+        Ok(())
+    }
+}
@@ -12,6 +12,7 @@ pub enum Value {
     Integer(u32),
     Boolean(bool),
     String(String),
+    Float(f32),
 }
 
 impl From<bool> for Value {
@@ -44,12 +45,19 @@ impl From<i32> for Value {
     }
 }
 
+impl From<f32> for Value {
+    fn from(item: f32) -> Self {
+        Value::Float(item)
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::Integer(i) => write!(f, "{}", i),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::String(s) => write!(f, "{:?}", s),
+            Value::Float(x) => write!(f, "{}", x),
         }
     }
 }
@@ -100,6 +108,9 @@ pub fn parse(input: &str) -> Vec<Value> {
             Rule::boolean => {
                 values.push(Value::Boolean(pair.as_str().parse().unwrap()));
             }
+            Rule::float => {
+                values.push(Value::Float(pair.as_str().parse().unwrap()));
+            }
             Rule::integer => {
                 values.push(Value::Integer(pair.as_str().parse().unwrap()));
             }
@@ -112,7 +123,3 @@ pub fn parse(input: &str) -> Vec<Value> {
 
     return values;
 }
-
-pub fn json_str_to_vec(json_str: &str) -> Result<Vec<String>, serde_json::Error> {
-    serde_json::from_str(json_str)
-}
\ No newline at end of file
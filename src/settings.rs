@@ -0,0 +1,44 @@
+//! 为 `smart_light_core` 中的配置提供 NVS 持久化，core 本身不依赖硬件
+
+use smart_light_core::{sampling::SamplingConfig, touch::TouchConfig};
+
+pub trait Setting:
+    serde::Serialize + for<'a> serde::Deserialize<'a> + std::fmt::Debug + Default + Copy
+{
+    /// 日志中显示的名称
+    const NAME: &'static str;
+
+    fn check(&self) -> anyhow::Result<()>;
+
+    /// 读取保存的配置，不存在或无效时使用默认值
+    fn load() -> anyhow::Result<Self> {
+        let config = crate::nvs::load::<Self>()?
+            .filter(|config| config.check().is_ok())
+            .unwrap_or_default();
+        log::info!("{} config: {:?}", Self::NAME, config);
+        Ok(config)
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        self.check()?;
+        crate::nvs::save(*self)?;
+        log::info!("{} config: {:?}", Self::NAME, self);
+        Ok(())
+    }
+}
+
+impl Setting for SamplingConfig {
+    const NAME: &'static str = "Sampling";
+
+    fn check(&self) -> anyhow::Result<()> {
+        self.validate()
+    }
+}
+
+impl Setting for TouchConfig {
+    const NAME: &'static str = "Touch";
+
+    fn check(&self) -> anyhow::Result<()> {
+        self.validate()
+    }
+}
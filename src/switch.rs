@@ -0,0 +1,265 @@
+use std::sync::{Arc, Mutex};
+
+use crate::actuator::{Actuator, Position, ServoActuator};
+use crate::fault::{Fault, FaultMonitor};
+use crate::miio::IoTFramework;
+use crate::parser::Value;
+use crate::verifier::{ActuationVerifier, Verdict, VerifyConfig};
+use smart_light_core::antiflicker::AntiFlicker;
+
+// 属性 x.2 模式: 0 有线和无线, 1 仅无线（忽略本地的触摸等物理触发）
+const MODE_WIRELESS: u32 = 1;
+
+// 第一路开关使用服务 2，之后的开关使用服务 9、10...
+pub fn siid(index: usize) -> u32 {
+    match index {
+        0 => 2,
+        n => 8 + n as u32,
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct GangConfig {
+    /// 开关路数，修改后重启生效
+    pub channels: u8,
+}
+
+impl Default for GangConfig {
+    fn default() -> Self {
+        Self { channels: 1 }
+    }
+}
+
+pub const MAX_CHANNELS: u8 = 3;
+
+/// 一路开关，对应米家的一个开关服务实例和一个舵机
+pub struct SwitchChannel {
+    pub siid: u32,
+    pub servo: Arc<Mutex<ServoActuator>>,
+    anti_flicker: Arc<Mutex<AntiFlicker>>,
+    pub faults: Arc<Mutex<FaultMonitor>>,
+    verifier: ActuationVerifier,
+    last_close_time: Arc<Mutex<Option<u64>>>,
+    // 舵机实际所处的开关位置，故障或出错时用于回滚属性 x.1
+    state: bool,
+}
+
+impl SwitchChannel {
+    pub fn new(
+        siid: u32,
+        mut servo: ServoActuator,
+        verifier: ActuationVerifier,
+        last_close_time: Arc<Mutex<Option<u64>>>,
+    ) -> anyhow::Result<Self> {
+        servo.move_to(if servo.is_press_mode() {
+            Position::Neutral
+        } else {
+            Position::Off
+        })?;
+        Ok(Self {
+            siid,
+            servo: Arc::new(Mutex::new(servo)),
+            anti_flicker: Arc::new(Mutex::new(AntiFlicker::default())),
+            faults: Arc::new(Mutex::new(FaultMonitor::default())),
+            verifier,
+            last_close_time,
+            state: false,
+        })
+    }
+
+    pub fn register(&self, miio: &mut IoTFramework) -> anyhow::Result<()> {
+        let anti_flicker = Arc::clone(&self.anti_flicker);
+        let anti_flicker_switch = Arc::clone(&self.anti_flicker);
+        let siid = self.siid;
+        miio.register(self.siid, 3, 0) // 故障
+            .register(self.siid, 5, false) // 灯具实际状态
+            .register(self.siid, 2, 0) // 模式
+            .on(move |e| log::info!("Switch {} mode: {}", siid, e))
+            .load()?
+            .register(self.siid, 4, false) // 防闪烁模式
+            .on(move |e| {
+                if let &Value::Boolean(value) = e {
+                    anti_flicker.lock().unwrap().set_enabled(value);
+                }
+            })
+            .load()?
+            .register(self.siid, 1, false) // 开关
+            .on(move |e| {
+                if let &Value::Boolean(value) = e {
+                    anti_flicker_switch.lock().unwrap().request(value);
+                }
+            });
+        Ok(())
+    }
+
+    /// 上电后恢复这一路上次保存的开关状态
+    pub fn restore(&mut self, miio: &mut IoTFramework) -> anyhow::Result<()> {
+        let on = miio.restore_cached(self.siid, 1)? == Some(&Value::Boolean(true));
+        if self.servo.lock().unwrap().is_press_mode() {
+            // 按压模式下无法从舵机位置得知开关状态，沿用上次记录的逻辑状态
+            self.state = on;
+        } else {
+            // 舵机上电时位于关的位置，需要时再转到开的位置
+            self.anti_flicker.lock().unwrap().request(on);
+        }
+        Ok(())
+    }
+
+    pub fn state(&self) -> bool {
+        self.state
+    }
+
+    /// 灯具实际所处的状态，未经光线传感器确认时与舵机位置一致
+    pub fn lamp_on(&self) -> bool {
+        self.verifier.lamp_on().unwrap_or(self.state)
+    }
+
+    pub fn verify_config(&self) -> Arc<Mutex<VerifyConfig>> {
+        self.verifier.config()
+    }
+
+    pub fn is_faulted(&self) -> bool {
+        self.faults.lock().unwrap().is_latched()
+    }
+
+    pub fn local_control_enabled(&self, miio: &IoTFramework) -> bool {
+        miio.get_from_cache(self.siid, 2) != Some(&Value::Integer(MODE_WIRELESS))
+    }
+
+    /// 本地物理触发或自动化发出的开关指令，无线模式或故障锁定时忽略，返回是否执行
+    pub fn switch_locally(&self, miio: &mut IoTFramework, on: bool) -> anyhow::Result<bool> {
+        if !self.local_control_enabled(miio) {
+            log::info!(
+                "Switch {}: local trigger ignored in wireless mode",
+                self.siid
+            );
+            return Ok(false);
+        }
+        let fault = self.faults.lock().unwrap().fault();
+        if fault != Fault::None {
+            log::warn!(
+                "Switch {}: local trigger ignored due to fault: {:?}",
+                self.siid,
+                fault
+            );
+            return Ok(false);
+        }
+        miio.set_property(self.siid, 1, Value::Boolean(on))?;
+        Ok(true)
+    }
+
+    /// 本地物理触发的切换
+    pub fn toggle(&self, miio: &mut IoTFramework) -> anyhow::Result<()> {
+        if let Some(&Value::Boolean(value)) = miio.get_from_cache(self.siid, 1) {
+            self.switch_locally(miio, !value)?;
+        }
+        Ok(())
+    }
+
+    /// 本地触发的关闭，例如延时关闭
+    pub fn turn_off(&self, miio: &mut IoTFramework) -> anyhow::Result<()> {
+        self.switch_locally(miio, false)?;
+        Ok(())
+    }
+
+    // 关灯引起的光照变化不应被当作触摸
+    fn mark_closed(&self) {
+        self.last_close_time.lock().unwrap().replace(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        );
+    }
+
+    fn actuate(&mut self, value: bool, illumination: Option<u16>) {
+        let fault = self.faults.lock().unwrap().fault();
+        if fault != Fault::None {
+            log::warn!("Switch {} is blocked by fault: {:?}", self.siid, fault);
+            return;
+        }
+        if value {
+            log::info!("Open the switch {}", self.siid);
+        } else {
+            self.mark_closed();
+            log::info!("Close the switch {}", self.siid);
+        }
+        let result = self.servo.lock().unwrap().move_to(Position::from(value));
+        match result {
+            Ok(_) => {
+                self.state = value;
+                self.faults.lock().unwrap().on_actuated();
+                self.verifier.start(value, illumination);
+            }
+            Err(e) => {
+                log::error!("Failed to drive the servo of switch {}: {:?}", self.siid, e);
+                self.faults.lock().unwrap().on_servo_error();
+            }
+        }
+    }
+
+    /// 根据光照变化确认开关是否生效，必要时重新按压
+    fn verify(&mut self, illumination: Option<u16>) {
+        if self.faults.lock().unwrap().is_latched() {
+            self.verifier.cancel();
+            return;
+        }
+        match self.verifier.poll(illumination) {
+            Some(Verdict::Retry(on)) => {
+                if !on {
+                    self.mark_closed();
+                }
+                let result = self.servo.lock().unwrap().retry(Position::from(on));
+                if let Err(e) = result {
+                    log::error!("Failed to drive the servo of switch {}: {:?}", self.siid, e);
+                    self.faults.lock().unwrap().on_servo_error();
+                }
+            }
+            Some(Verdict::Failed) => {
+                log::error!("Switch {}: lamp did not respond", self.siid);
+                self.faults.lock().unwrap().on_unverified();
+            }
+            Some(Verdict::Confirmed) | None => {}
+        }
+    }
+
+    pub fn tick(
+        &mut self,
+        miio: &mut IoTFramework,
+        illumination: Option<u16>,
+        temperature: Option<f32>,
+    ) {
+        let result = self.servo.lock().unwrap().tick();
+        if let Err(e) = result {
+            log::error!("Failed to drive the servo of switch {}: {:?}", self.siid, e);
+            self.faults.lock().unwrap().on_servo_error();
+        }
+
+        let request = self.anti_flicker.lock().unwrap().poll(self.state);
+        if let Some(value) = request {
+            self.actuate(value, illumination);
+        }
+
+        self.verify(illumination);
+
+        let fault = {
+            let mut faults = self.faults.lock().unwrap();
+            if let Some(celsius) = temperature {
+                faults.on_temperature(celsius);
+            }
+            faults.tick();
+            faults.fault()
+        };
+        let _ = miio.set_property(self.siid, 3, Value::Integer(fault as u32));
+        let lamp_on = self.lamp_on();
+        if miio.get_from_cache(self.siid, 5) != Some(&Value::Boolean(lamp_on)) {
+            let _ = miio.set_property(self.siid, 5, Value::Boolean(lamp_on));
+        }
+
+        if !self.anti_flicker.lock().unwrap().is_pending()
+            && miio.get_from_cache(self.siid, 1) != Some(&Value::Boolean(self.state))
+        {
+            let _ = miio.set_property(self.siid, 1, Value::Boolean(self.state));
+        }
+    }
+}
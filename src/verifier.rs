@@ -0,0 +1,177 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// 重新按压次数和等待时间的上限，避免故障前反复按压太久
+const MAX_RETRIES: u8 = 5;
+const MAX_DELAY_MS: u32 = 10000;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy)]
+pub struct VerifyConfig {
+    /// 灯具与光线传感器不在同一处时可以关闭校验，默认只校验第一路
+    pub enabled: bool,
+    /// 未检测到光照变化时重新按压的次数
+    pub retries: u8,
+    /// 开关后等待灯光稳定再比较光照，单位 ms
+    pub delay_ms: u32,
+    /// 开关前后光照（ADC 原始值）的最小变化量
+    pub threshold: u16,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retries: 2,
+            delay_ms: 2000,
+            threshold: 100,
+        }
+    }
+}
+
+impl VerifyConfig {
+    /// 读取某一路的设置，第一路找不到时迁移旧版本所有开关共用的设置
+    pub fn load(channel: usize) -> anyhow::Result<Self> {
+        let key = format!("ch{}", channel);
+        if let Some(config) = crate::nvs::load_from::<VerifyConfig>(&key)? {
+            return Ok(config);
+        }
+        if channel == 0 {
+            if let Some(config) = crate::nvs::load::<VerifyConfig>()? {
+                log::info!("Migrate actuation verify config {:?} to {}", config, key);
+                crate::nvs::save_to(config, &key)?;
+                return Ok(config);
+            }
+        }
+        Ok(Self {
+            enabled: channel == 0,
+            ..Default::default()
+        })
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.retries > MAX_RETRIES {
+            anyhow::bail!("retries must not exceed {}", MAX_RETRIES);
+        }
+        if self.delay_ms > MAX_DELAY_MS {
+            anyhow::bail!("delay_ms must not exceed {}", MAX_DELAY_MS);
+        }
+        if self.threshold == 0 {
+            anyhow::bail!("threshold must be positive");
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, channel: usize) -> anyhow::Result<()> {
+        self.validate()?;
+        crate::nvs::save_to(*self, &format!("ch{}", channel))?;
+        log::info!("Actuation verify config of channel {}: {:?}", channel, self);
+        Ok(())
+    }
+}
+
+struct Pending {
+    on: bool,
+    before: u16,
+    at: Instant,
+    attempts: u8,
+}
+
+pub enum Verdict {
+    /// 光照变化与开关方向一致
+    Confirmed,
+    /// 未检测到变化，需要再按一次
+    Retry(bool),
+    /// 重试次数用尽仍未检测到变化
+    Failed,
+}
+
+/// 比较开关前后的光照，确认灯具是否真的改变了状态
+pub struct ActuationVerifier {
+    config: Arc<Mutex<VerifyConfig>>,
+    pending: Option<Pending>,
+    lamp_on: Option<bool>,
+}
+
+impl ActuationVerifier {
+    pub fn new(channel: usize) -> anyhow::Result<Self> {
+        let config = VerifyConfig::load(channel)?;
+        log::info!(
+            "Actuation verify config of channel {}: {:?}",
+            channel,
+            config
+        );
+        Ok(Self {
+            config: Arc::new(Mutex::new(config)),
+            pending: None,
+            lamp_on: None,
+        })
+    }
+
+    /// 与本地接口共享的设置，修改后下一次开关时生效
+    pub fn config(&self) -> Arc<Mutex<VerifyConfig>> {
+        Arc::clone(&self.config)
+    }
+
+    /// 灯具实际所处的状态，未经光线传感器确认时为 None
+    pub fn lamp_on(&self) -> Option<bool> {
+        self.lamp_on
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// 舵机动作完成，记录开关前的光照
+    pub fn start(&mut self, on: bool, illumination: Option<u16>) {
+        self.pending = None;
+        if !self.config.lock().unwrap().enabled {
+            self.lamp_on = Some(on);
+            return;
+        }
+        match illumination {
+            Some(before) => {
+                self.pending = Some(Pending {
+                    on,
+                    before,
+                    at: Instant::now(),
+                    attempts: 0,
+                })
+            }
+            None => self.lamp_on = None,
+        }
+    }
+
+    pub fn poll(&mut self, illumination: Option<u16>) -> Option<Verdict> {
+        let config = *self.config.lock().unwrap();
+        let (pending, value) = (self.pending.as_mut()?, illumination?);
+        if pending.at.elapsed() < Duration::from_millis(config.delay_ms as u64) {
+            return None;
+        }
+        // 传感器越暗读数越高，开灯后读数应当下降
+        let confirmed = if pending.on {
+            value.saturating_add(config.threshold) < pending.before
+        } else {
+            value > pending.before.saturating_add(config.threshold)
+        };
+        if confirmed {
+            self.lamp_on = Some(pending.on);
+            self.pending = None;
+            return Some(Verdict::Confirmed);
+        }
+        if pending.attempts < config.retries {
+            pending.attempts += 1;
+            pending.at = Instant::now();
+            log::warn!(
+                "Toggle not confirmed by light sensor, retry {}/{}",
+                pending.attempts,
+                config.retries
+            );
+            return Some(Verdict::Retry(pending.on));
+        }
+        self.lamp_on = Some(!pending.on);
+        self.pending = None;
+        Some(Verdict::Failed)
+    }
+}